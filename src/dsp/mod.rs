@@ -0,0 +1,19 @@
+mod mdct;
+
+use alloc::vec::Vec;
+
+pub use mdct::imdct;
+
+/// Builds the Vorbis power-complementary window CELT uses for its MDCT
+/// overlap-add: `sin(pi/2 * sin^2(pi*(n+1/2)/(2*n)))` over the full `2*n`-sample
+/// window, where `n` is the number of samples CELT outputs per frame.
+pub fn vorbis_window(n: usize) -> Vec<f32> {
+    let full_len = 2 * n;
+
+    (0..full_len)
+        .map(|i| {
+            let inner = crate::math::sin(core::f32::consts::PI * (i as f32 + 0.5) / full_len as f32);
+            crate::math::sin(core::f32::consts::FRAC_PI_2 * inner * inner)
+        })
+        .collect()
+}
@@ -1,4 +1,8 @@
-use crate::{Channels, Error};
+use crate::{range_coding::RangeEncoder, Channels, Error};
+use alloc::vec::Vec;
+
+// The number of bits used to range-code each quantized sample.
+const SAMPLE_BITS: u32 = 16;
 
 pub struct Encoder {
     _sample_rate: u32,
@@ -11,7 +15,17 @@ impl Encoder {
         Self { _sample_rate: sample_rate, _bit_rate: bit_rate, _channels: channels }
     }
 
-    pub fn encode_f32(&mut self, _frame: &[f32]) -> Result<Vec<u8>, Error> {
-        Ok(vec![])
+    // TODO(bschwind) - This range-codes the quantized samples directly as raw
+    // bits. A real Opus bitstream (TOC byte, SILK/CELT analysis, PVQ-coded
+    // spectral shapes, etc) isn't produced here yet.
+    pub fn encode_f32(&mut self, frame: &[f32]) -> Result<Vec<u8>, Error> {
+        let mut range_encoder = RangeEncoder::new();
+
+        for &sample in frame {
+            let quantized = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16 as u16;
+            range_encoder.encode_bits(quantized as u32, SAMPLE_BITS);
+        }
+
+        Ok(range_encoder.finalize())
     }
 }
@@ -1,9 +1,24 @@
+// `std` is only needed for its test harness; the library itself only needs
+// `alloc` (a `Vec`-backed output buffer and, for the Ogg comment header, a
+// `String`) plus the `core` equivalents of what used to be `std` conveniences
+// (`TryFrom`, `mem::size_of`, and - since `core::f32` has no libm - the
+// hand-rolled trig/pow helpers in `math`).
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod celt;
 mod decoder;
+mod dsp;
 mod encoder;
+mod math;
+mod ogg;
 mod range_coding;
+mod silk;
 
 pub use decoder::Decoder;
 pub use encoder::Encoder;
+pub use ogg::{OggOpusReader, OpusHead, OpusTags};
 
 #[derive(Debug)]
 pub enum Error {
@@ -14,6 +29,14 @@ pub enum Error {
     InvalidFrameCount,
     InvalidOpusPadding,
     InvalidCodecConfig,
+    InvalidOggPage,
+    InvalidOggCapturePattern,
+    InvalidOpusHead,
+    InvalidOpusTags,
+    // Hybrid-mode packets decode their SILK low band but have no CELT
+    // high-band mixing implemented yet, so they're rejected outright
+    // instead of silently decoded as SILK-only (wrong-bandwidth) audio.
+    UnsupportedHybridMode,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -56,6 +79,22 @@ enum FrameSizeMs {
     Sixty,
 }
 
+impl FrameSizeMs {
+    // The number of time-domain samples a frame covers at `sample_rate`.
+    fn samples(self, sample_rate: u32) -> usize {
+        let micros = match self {
+            FrameSizeMs::TwoPointFive => 2_500,
+            FrameSizeMs::Five => 5_000,
+            FrameSizeMs::Ten => 10_000,
+            FrameSizeMs::Twenty => 20_000,
+            FrameSizeMs::Forty => 40_000,
+            FrameSizeMs::Sixty => 60_000,
+        };
+
+        (sample_rate as u64 * micros / 1_000_000) as usize
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 struct CodecConfig {
     mode: CodecMode,
@@ -69,3 +108,20 @@ struct TableOfContentsHeader {
     channels: Channels,
     frames_per_packet: FramesPerPacket,
 }
+
+// Exercises the public API using only `alloc` types (no `std::io`,
+// `std::fs`, or other `std`-only conveniences), as a readable example of
+// decode-path usage that never reaches for `std`. This runs under the
+// standard test harness, which always links `std` regardless of the `std`
+// feature flag, so it can't by itself prove this crate still compiles
+// under a true no_std build - that's what the `no_std` job in
+// `.github/workflows/ci.yml` (`cargo check --no-default-features`) is for.
+#[test]
+fn test_decode_under_no_std_and_alloc() {
+    use alloc::vec;
+
+    let silent_celt_packet = vec![0b1111_1000, 0x00];
+    let mut decoder = Decoder::new(48_000, Channels::Mono);
+
+    decoder.decode_f32(&silent_celt_packet).unwrap();
+}
@@ -0,0 +1,139 @@
+//! `core`-only floating point helpers (sin/cos/sqrt/ln/exp/pow) for the DSP
+//! and CELT code. `core::f32` doesn't expose these - they're libm-backed
+//! methods that only exist on `std::f32` - so under `no_std` we can't just
+//! call `x.sin()`. These are simple, not hardware-accelerated, but accurate
+//! enough for the range coder's twiddle factors and energy-domain math.
+
+const TWO_PI: f32 = core::f32::consts::PI * 2.0;
+
+pub fn sin(x: f32) -> f32 {
+    cos(x - core::f32::consts::FRAC_PI_2)
+}
+
+pub fn cos(x: f32) -> f32 {
+    let mut x = x % TWO_PI;
+
+    if x > core::f32::consts::PI {
+        x -= TWO_PI;
+    } else if x < -core::f32::consts::PI {
+        x += TWO_PI;
+    }
+
+    // A degree-10 Taylor series for cos(x), accurate to single-precision
+    // over the [-pi, pi] range reduced above.
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    let x6 = x4 * x2;
+    let x8 = x4 * x4;
+    let x10 = x8 * x2;
+
+    1.0 - x2 / 2.0 + x4 / 24.0 - x6 / 720.0 + x8 / 40_320.0 - x10 / 3_628_800.0
+}
+
+pub fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    // The classic fast-inverse-sqrt bit hack, seeding a couple of Newton
+    // iterations for 1/sqrt(x), then multiplying back through by `x`.
+    let i = x.to_bits();
+    let mut y = f32::from_bits(0x5f37_59df - (i >> 1));
+
+    for _ in 0..4 {
+        y *= 1.5 - 0.5 * x * y * y;
+    }
+
+    x * y
+}
+
+pub fn ln(x: f32) -> f32 {
+    if x <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+
+    // Decompose x = m * 2^e with m in [1, 2) by editing the float's exponent
+    // bits directly, then use the atanh series for ln(m).
+    let bits = x.to_bits();
+    let exponent = ((bits >> 23) & 0xFF) as i32 - 127;
+    let m = f32::from_bits((bits & 0x007F_FFFF) | (127 << 23));
+
+    let z = (m - 1.0) / (m + 1.0);
+    let z2 = z * z;
+    let atanh = z * (1.0 + z2 * (1.0 / 3.0 + z2 * (1.0 / 5.0 + z2 * (1.0 / 7.0))));
+
+    exponent as f32 * core::f32::consts::LN_2 + 2.0 * atanh
+}
+
+pub fn exp(x: f32) -> f32 {
+    // Range-reduce x = n*ln2 + r with r in [-ln2/2, ln2/2], so
+    // exp(x) = 2^n * exp(r), and Taylor-expand the now-small exp(r).
+    let n = round_to_i32(x / core::f32::consts::LN_2);
+    let r = x - n as f32 * core::f32::consts::LN_2;
+
+    let exp_r = 1.0 + r + r * r / 2.0 + r * r * r / 6.0 + r * r * r * r / 24.0;
+
+    ldexp(exp_r, n)
+}
+
+pub fn powf(base: f32, exponent: f32) -> f32 {
+    if base == 0.0 {
+        return 0.0;
+    }
+
+    exp(exponent * ln(base))
+}
+
+pub fn powi(base: f32, exponent: i32) -> f32 {
+    let mut result = 1.0f32;
+    let mut remaining = exponent.unsigned_abs();
+    let mut squared = base;
+
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result *= squared;
+        }
+
+        squared *= squared;
+        remaining >>= 1;
+    }
+
+    if exponent < 0 {
+        1.0 / result
+    } else {
+        result
+    }
+}
+
+fn round_to_i32(x: f32) -> i32 {
+    (x + if x >= 0.0 { 0.5 } else { -0.5 }) as i32
+}
+
+/// Rounds `x` to the nearest integer, ties away from zero. `core::f64` has
+/// no libm, so `f64::round` isn't available under `no_std` - this is the
+/// `f64` counterpart to [`round_to_i32`]'s rounding rule, kept as a whole
+/// `f64` (rather than truncating through `i32`) since callers need it for
+/// ratios too large to round-trip through an `i32`.
+pub(crate) fn round(x: f64) -> f64 {
+    if x >= 0.0 {
+        (x + 0.5) as i64 as f64
+    } else {
+        (x - 0.5) as i64 as f64
+    }
+}
+
+// Multiplies `x` by 2^n via the exponent bits directly (no transcendental
+// math needed).
+fn ldexp(x: f32, n: i32) -> f32 {
+    let bits = x.to_bits();
+    let exponent = ((bits >> 23) & 0xFF) as i32 + n;
+
+    if exponent <= 0 {
+        return 0.0;
+    }
+    if exponent >= 255 {
+        return f32::INFINITY;
+    }
+
+    f32::from_bits((bits & 0x807F_FFFF) | ((exponent as u32) << 23))
+}
@@ -0,0 +1,395 @@
+use crate::{Decoder, Error};
+use alloc::{string::String, vec::Vec};
+
+#[cfg(test)]
+use alloc::{string::ToString, vec};
+
+const CAPTURE_PATTERN: &[u8; 4] = b"OggS";
+const OPUS_HEAD_MAGIC: &[u8; 8] = b"OpusHead";
+const OPUS_TAGS_MAGIC: &[u8; 8] = b"OpusTags";
+
+/// The parsed `OpusHead` identification header, the first packet in every
+/// Ogg Opus stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpusHead {
+    pub version: u8,
+    pub channel_count: u8,
+    pub pre_skip: u16,
+    pub input_sample_rate: u32,
+    pub output_gain: i16,
+    pub channel_mapping_family: u8,
+}
+
+impl OpusHead {
+    fn parse(packet: &[u8]) -> Result<Self, Error> {
+        if packet.len() < 19 || &packet[0..8] != OPUS_HEAD_MAGIC {
+            return Err(Error::InvalidOpusHead);
+        }
+
+        Ok(Self {
+            version: packet[8],
+            channel_count: packet[9],
+            pre_skip: u16::from_le_bytes([packet[10], packet[11]]),
+            input_sample_rate: u32::from_le_bytes([
+                packet[12], packet[13], packet[14], packet[15],
+            ]),
+            output_gain: i16::from_le_bytes([packet[16], packet[17]]),
+            channel_mapping_family: packet[18],
+        })
+    }
+}
+
+/// The parsed `OpusTags` comment header, the second packet in every Ogg
+/// Opus stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpusTags {
+    pub vendor: String,
+    pub comments: Vec<String>,
+}
+
+impl OpusTags {
+    fn parse(packet: &[u8]) -> Result<Self, Error> {
+        if packet.len() < 8 || &packet[0..8] != OPUS_TAGS_MAGIC {
+            return Err(Error::InvalidOpusTags);
+        }
+
+        let mut offset = 8;
+
+        let vendor_len = read_u32_le(packet, offset)? as usize;
+        offset += 4;
+        let vendor = read_string(packet, offset, vendor_len)?;
+        offset += vendor_len;
+
+        let comment_count = read_u32_le(packet, offset)? as usize;
+        offset += 4;
+
+        let mut comments = Vec::with_capacity(comment_count);
+        for _ in 0..comment_count {
+            let len = read_u32_le(packet, offset)? as usize;
+            offset += 4;
+            comments.push(read_string(packet, offset, len)?);
+            offset += len;
+        }
+
+        Ok(Self { vendor, comments })
+    }
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = data.get(offset..offset + 4).ok_or(Error::InvalidOpusTags)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_string(data: &[u8], offset: usize, len: usize) -> Result<String, Error> {
+    let bytes = data.get(offset..offset + len).ok_or(Error::InvalidOpusTags)?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+// One parsed Ogg page: the capture pattern and version are validated up
+// front, and the segment table has already been sliced out of the page body
+// so packet reconstruction doesn't need to re-walk the lacing values.
+struct OggPage<'a> {
+    segments: Vec<&'a [u8]>,
+}
+
+struct OggPageIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> OggPageIterator<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for OggPageIterator<'a> {
+    type Item = Result<OggPage<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        Some(self.parse_page())
+    }
+}
+
+impl<'a> OggPageIterator<'a> {
+    fn parse_page(&mut self) -> Result<OggPage<'a>, Error> {
+        // Capture pattern (4), version (1), header type (1), granule
+        // position (8), serial number (4), page sequence (4), CRC (4),
+        // segment count (1).
+        const PAGE_HEADER_LEN: usize = 27;
+
+        if self.data.len() < PAGE_HEADER_LEN {
+            return Err(Error::InvalidOggPage);
+        }
+
+        if &self.data[0..4] != CAPTURE_PATTERN {
+            return Err(Error::InvalidOggCapturePattern);
+        }
+
+        let segment_count = self.data[26] as usize;
+        let header_len = PAGE_HEADER_LEN + segment_count;
+
+        if self.data.len() < header_len {
+            return Err(Error::InvalidOggPage);
+        }
+
+        let lacing_values = &self.data[PAGE_HEADER_LEN..header_len];
+        let body_len: usize = lacing_values.iter().map(|&v| v as usize).sum();
+
+        if self.data.len() < header_len + body_len {
+            return Err(Error::InvalidOggPage);
+        }
+
+        let mut body = &self.data[header_len..header_len + body_len];
+        let mut segments = Vec::with_capacity(segment_count);
+
+        for &lacing in lacing_values {
+            let (segment, rest) = body.split_at(lacing as usize);
+            segments.push(segment);
+            body = rest;
+        }
+
+        self.data = &self.data[header_len + body_len..];
+
+        Ok(OggPage { segments })
+    }
+}
+
+// Reassembles packets out of the lacing-delimited segments of successive
+// pages. A segment shorter than 255 bytes terminates the packet it belongs
+// to; a full 255-byte segment means the packet continues into the next
+// segment (possibly on the next page).
+struct PacketIterator<'a> {
+    pages: OggPageIterator<'a>,
+    current_page_segments: alloc::vec::IntoIter<&'a [u8]>,
+    partial: Vec<u8>,
+    errored: bool,
+}
+
+impl<'a> PacketIterator<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            pages: OggPageIterator::new(data),
+            current_page_segments: Vec::new().into_iter(),
+            partial: Vec::new(),
+            errored: false,
+        }
+    }
+}
+
+impl<'a> Iterator for PacketIterator<'a> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.errored {
+            return None;
+        }
+
+        loop {
+            if let Some(segment) = self.current_page_segments.next() {
+                self.partial.extend_from_slice(segment);
+
+                if segment.len() < 255 {
+                    return Some(Ok(core::mem::take(&mut self.partial)));
+                }
+
+                continue;
+            }
+
+            match self.pages.next() {
+                Some(Ok(page)) => self.current_page_segments = page.segments.into_iter(),
+                Some(Err(err)) => {
+                    self.errored = true;
+                    return Some(Err(err));
+                },
+                None => {
+                    return if self.partial.is_empty() {
+                        None
+                    } else {
+                        Some(Ok(core::mem::take(&mut self.partial)))
+                    };
+                },
+            }
+        }
+    }
+}
+
+/// Parses an Ogg-encapsulated Opus stream and yields the audio packets it
+/// contains, ready to hand to [`Decoder::decode_f32`].
+pub struct OggOpusReader<'a> {
+    head: OpusHead,
+    tags: OpusTags,
+    packets: PacketIterator<'a>,
+}
+
+impl<'a> OggOpusReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let mut packets = PacketIterator::new(data);
+
+        let ident_packet = packets.next().ok_or(Error::InvalidOggPage)??;
+        let head = OpusHead::parse(&ident_packet)?;
+
+        let comment_packet = packets.next().ok_or(Error::InvalidOggPage)??;
+        let tags = OpusTags::parse(&comment_packet)?;
+
+        Ok(Self { head, tags, packets })
+    }
+
+    pub fn head(&self) -> &OpusHead {
+        &self.head
+    }
+
+    pub fn tags(&self) -> &OpusTags {
+        &self.tags
+    }
+
+    /// Decodes every remaining audio packet with `decoder` and trims the
+    /// leading `pre_skip` samples `head` declares, so playback starts at the
+    /// same point the original encoder intended.
+    pub fn decode_all(mut self, decoder: &mut Decoder) -> Result<Vec<f32>, Error> {
+        let mut samples = Vec::new();
+
+        for packet in self.by_ref() {
+            samples.extend(decoder.decode_f32(&packet?)?);
+        }
+
+        let skip = (self.head.pre_skip as usize).min(samples.len());
+        Ok(samples.split_off(skip))
+    }
+}
+
+impl<'a> Iterator for OggOpusReader<'a> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.packets.next()
+    }
+}
+
+#[cfg(test)]
+fn build_page(serial: u32, sequence: u32, last_page: bool, packet: &[u8]) -> Vec<u8> {
+    let mut lacing = vec![];
+    let mut remaining = packet.len();
+    while remaining >= 255 {
+        lacing.push(255u8);
+        remaining -= 255;
+    }
+    lacing.push(remaining as u8);
+
+    let mut page = Vec::new();
+    page.extend_from_slice(CAPTURE_PATTERN);
+    page.push(0); // version
+    page.push(if last_page { 0x4 } else { 0x0 }); // header type
+    page.extend_from_slice(&0u64.to_le_bytes()); // granule position
+    page.extend_from_slice(&serial.to_le_bytes());
+    page.extend_from_slice(&sequence.to_le_bytes());
+    page.extend_from_slice(&0u32.to_le_bytes()); // CRC, unchecked by the parser
+    page.push(lacing.len() as u8);
+    page.extend_from_slice(&lacing);
+    page.extend_from_slice(packet);
+
+    page
+}
+
+#[test]
+fn test_parse_ogg_opus_stream() {
+    let head_packet = {
+        let mut p = Vec::new();
+        p.extend_from_slice(OPUS_HEAD_MAGIC);
+        p.push(1); // version
+        p.push(2); // channel count
+        p.extend_from_slice(&312u16.to_le_bytes()); // pre_skip
+        p.extend_from_slice(&48_000u32.to_le_bytes()); // input sample rate
+        p.extend_from_slice(&0i16.to_le_bytes()); // output gain
+        p.push(0); // channel mapping family
+        p
+    };
+
+    let tags_packet = {
+        let mut p = Vec::new();
+        p.extend_from_slice(OPUS_TAGS_MAGIC);
+        let vendor = b"test vendor";
+        p.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        p.extend_from_slice(vendor);
+        p.extend_from_slice(&1u32.to_le_bytes()); // comment count
+        let comment = b"TITLE=test";
+        p.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        p.extend_from_slice(comment);
+        p
+    };
+
+    let audio_packet = vec![0xAAu8; 40];
+
+    let mut stream = Vec::new();
+    stream.extend(build_page(1, 0, false, &head_packet));
+    stream.extend(build_page(1, 1, false, &tags_packet));
+    stream.extend(build_page(1, 2, true, &audio_packet));
+
+    let mut reader = OggOpusReader::new(&stream).unwrap();
+
+    assert_eq!(reader.head().channel_count, 2);
+    assert_eq!(reader.head().pre_skip, 312);
+    assert_eq!(reader.head().input_sample_rate, 48_000);
+    assert_eq!(reader.tags().vendor, "test vendor");
+    assert_eq!(reader.tags().comments, vec!["TITLE=test".to_string()]);
+
+    let packet = reader.next().unwrap().unwrap();
+    assert_eq!(packet, audio_packet);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn test_packet_spanning_multiple_pages() {
+    let head_packet = {
+        let mut p = Vec::new();
+        p.extend_from_slice(OPUS_HEAD_MAGIC);
+        p.push(1);
+        p.push(1);
+        p.extend_from_slice(&0u16.to_le_bytes());
+        p.extend_from_slice(&48_000u32.to_le_bytes());
+        p.extend_from_slice(&0i16.to_le_bytes());
+        p.push(0);
+        p
+    };
+
+    let tags_packet = {
+        let mut p = Vec::new();
+        p.extend_from_slice(OPUS_TAGS_MAGIC);
+        p.extend_from_slice(&0u32.to_le_bytes());
+        p.extend_from_slice(&0u32.to_le_bytes());
+        p
+    };
+
+    // A packet exactly 255 bytes long needs a terminating zero-length
+    // segment, which this test deliberately spans across two pages.
+    let big_packet: Vec<u8> = (0..255u32).map(|n| n as u8).collect();
+
+    let mut first_page = build_page(7, 0, false, &head_packet);
+    first_page.extend(build_page(7, 1, false, &tags_packet));
+
+    let mut page_with_full_segment = Vec::new();
+    page_with_full_segment.extend_from_slice(CAPTURE_PATTERN);
+    page_with_full_segment.push(0);
+    page_with_full_segment.push(0x0);
+    page_with_full_segment.extend_from_slice(&0u64.to_le_bytes());
+    page_with_full_segment.extend_from_slice(&7u32.to_le_bytes());
+    page_with_full_segment.extend_from_slice(&2u32.to_le_bytes());
+    page_with_full_segment.extend_from_slice(&0u32.to_le_bytes());
+    page_with_full_segment.push(1);
+    page_with_full_segment.push(255);
+    page_with_full_segment.extend_from_slice(&big_packet);
+
+    let terminator_page = build_page(7, 3, true, &[]);
+
+    let mut stream = Vec::new();
+    stream.extend(first_page);
+    stream.extend(page_with_full_segment);
+    stream.extend(terminator_page);
+
+    let mut reader = OggOpusReader::new(&stream).unwrap();
+    let packet = reader.next().unwrap().unwrap();
+    assert_eq!(packet, big_packet);
+}
@@ -5,7 +5,6 @@ const CODE_BITS: i32 = 32;
 // The number of bits to output at a time.
 const SYMBOL_BITS: i32 = 8;
 // The maximum symbol value.
-#[allow(unused)]
 const SYMBOL_MAX: u32 = (1u32 << SYMBOL_BITS) - 1;
 // Bits to shift by to move a symbol into the high-order position.
 const CODE_SHIFT: i32 = CODE_BITS - SYMBOL_BITS - 1;
@@ -15,10 +14,33 @@ const CODE_TOP: u32 = 1u32 << (CODE_BITS - 1);
 const CODE_BOTTOM: u32 = CODE_TOP >> SYMBOL_BITS;
 // The number of bits available for the last, partial symbol in the code field.
 const CODE_EXTRA: i32 = (CODE_BITS - 2) % SYMBOL_BITS + 1;
-const WINDOW_SIZE: i32 = (std::mem::size_of::<u32>() * 8) as i32;
+const WINDOW_SIZE: i32 = (core::mem::size_of::<u32>() * 8) as i32;
 
 mod range_decoder;
 mod range_encoder;
 
 pub use range_decoder::RangeDecoder;
 pub use range_encoder::RangeEncoder;
+
+/// Returns the position of the highest set bit, i.e. `floor(log2(v)) + 1`, or
+/// `0` when `v` is `0`. Shared by the encoder and decoder's `decode_u32`/`encode_u32`
+/// bit-length calculations.
+pub(super) fn ilog(mut v: u32) -> u32 {
+    let mut ret = (v != 0) as u32;
+    let mut m = ((v & 0xFFFF0000 != 0) as u32) << 4;
+
+    v >>= m;
+    ret |= m;
+    m = ((v & 0xFF00 != 0) as u32) << 3;
+    v >>= m;
+    ret |= m;
+    m = ((v & 0xF0 != 0) as u32) << 2;
+    v >>= m;
+    ret |= m;
+    m = ((v & 0xC != 0) as u32) << 1;
+    v >>= m;
+    ret |= m;
+    ret += (v & 0x2 != 0) as u32;
+
+    ret
+}
@@ -1,19 +1,6 @@
-// The number of bits to use for the range-coded part of unsigned integers.
-const UINT_BITS: u32 = 8;
-// The total number of bits in each of the state registers.
-const CODE_BITS: i32 = 32;
-// The number of bits to output at a time.
-const SYMBOL_BITS: i32 = 8;
-// The maximum symbol value.
-#[allow(unused)]
-const SYMBOL_MAX: u32 = (1u32 << SYMBOL_BITS) - 1;
-// Carry bit of the high-order range symbol.
-const CODE_TOP: u32 = 1u32 << (CODE_BITS - 1);
-// Low-order bit of the high-order range symbol.
-const CODE_BOTTOM: u32 = CODE_TOP >> SYMBOL_BITS;
-// The number of bits available for the last, partial symbol in the code field.
-const CODE_EXTRA: i32 = (CODE_BITS - 2) % SYMBOL_BITS + 1;
-const WINDOW_SIZE: i32 = (std::mem::size_of::<u32>() * 8) as i32;
+use crate::range_coding::{
+    CODE_BITS, CODE_BOTTOM, CODE_EXTRA, CODE_TOP, SYMBOL_BITS, SYMBOL_MAX, UINT_BITS, WINDOW_SIZE,
+};
 
 pub struct RangeDecoder<'a> {
     frame_data: &'a [u8],
@@ -29,8 +16,12 @@ pub struct RangeDecoder<'a> {
 
     bit_decoder: BitDecoder,
 
-    // The leftover bit on the first input byte. The least significant bit.
-    leftover_bit: bool,
+    // The most recently read input byte, held back by one renormalization
+    // step so its bits can be combined with the next byte. The encoder
+    // doesn't know how many bits of a byte belong to the symbol it
+    // terminates until it sees what comes after, so the decoder mirrors
+    // that by always looking one byte ahead before committing bits to `val`.
+    rem: u8,
 }
 
 struct BitDecoder {
@@ -70,14 +61,8 @@ impl<'a> RangeDecoder<'a> {
         let ext = 0;
         let bit_decoder = BitDecoder::default();
 
-        let mut myself = Self {
-            frame_data,
-            rng,
-            val,
-            ext,
-            bit_decoder,
-            leftover_bit: first_input_byte & 1 == 1,
-        };
+        let mut myself =
+            Self { frame_data, rng, val, ext, bit_decoder, rem: first_input_byte };
 
         myself.renormalize();
 
@@ -103,7 +88,7 @@ impl<'a> RangeDecoder<'a> {
         frequency_total -= 1;
 
         // The number of bits required to store (frequency_total - 1) in two's complement.
-        let frequency_total_bits = Self::ilog(frequency_total);
+        let frequency_total_bits = super::ilog(frequency_total);
 
         if frequency_total_bits > UINT_BITS {
             // The top 8 bits of t are decoded using temp:
@@ -131,6 +116,46 @@ impl<'a> RangeDecoder<'a> {
         }
     }
 
+    // Decodes a symbol from a cumulative-frequency table: `cumulative[i]` is
+    // the exclusive upper edge of symbol `i`'s bucket (so `cumulative[i] -
+    // cumulative[i-1]` is how much of `frequency_total` that symbol claims,
+    // and `cumulative`'s last entry is `frequency_total` itself) - the same
+    // ascending-bucket convention `decode_u32`'s implicit `value..value+1`
+    // buckets use, but letting each symbol claim an arbitrarily-sized slice
+    // of the total instead of all being equal width. Used for distributions
+    // that aren't flat, e.g. SILK's per-coefficient NLSF residual, which is
+    // far likelier to be near zero than out at the tails.
+    pub(crate) fn decode_cdf(&mut self, cumulative: &[u32]) -> usize {
+        let frequency_total = *cumulative.last().expect("cumulative table must not be empty");
+
+        let t = self.decode(frequency_total);
+        let symbol = cumulative.iter().position(|&edge| t < edge).unwrap_or(cumulative.len() - 1);
+
+        let low = if symbol == 0 { 0 } else { cumulative[symbol - 1] };
+        let high = cumulative[symbol];
+        self.update(low, high, frequency_total);
+
+        symbol
+    }
+
+    // Decodes a single bit whose probability of being `true` is `1 / 2^logp`,
+    // mirroring the common `ec_dec_bit_logp` shorthand built out of the same
+    // `decode`/`update` primitives `decode_u32` uses, rather than a dedicated
+    // fast path.
+    pub fn decode_bit_logp(&mut self, logp: u32) -> bool {
+        let frequency_total = 1u32 << logp;
+        let t = self.decode(frequency_total);
+        let bit = t == frequency_total - 1;
+
+        if bit {
+            self.update(frequency_total - 1, frequency_total, frequency_total);
+        } else {
+            self.update(0, frequency_total - 1, frequency_total);
+        }
+
+        bit
+    }
+
     // TODO(bschwind) - Return a u16 here?
     fn decode(&mut self, frequency_total: u32) -> u32 {
         self.ext = self.rng / frequency_total;
@@ -139,7 +164,7 @@ impl<'a> RangeDecoder<'a> {
         frequency_total - (s + 1).min(frequency_total)
     }
 
-    fn decode_bits(&mut self, bits: u32) -> u32 {
+    pub(crate) fn decode_bits(&mut self, bits: u32) -> u32 {
         let mut window = self.bit_decoder.end_window;
         let mut available = self.bit_decoder.num_end_bits;
 
@@ -190,34 +215,15 @@ impl<'a> RangeDecoder<'a> {
         while self.rng <= CODE_BOTTOM {
             self.bit_decoder.num_bits_total += SYMBOL_BITS;
             self.rng <<= SYMBOL_BITS;
-            let next_byte = self.read_byte();
 
-            let sym = next_byte | if self.leftover_bit { 1 } else { 0 };
-            self.leftover_bit = next_byte & 1 == 1;
+            // Combine the held-back byte with the next one so the bits
+            // the previous renormalization step didn't consume carry
+            // forward, mirroring the encoder's `carry_out`.
+            let prev = self.rem as u32;
+            self.rem = self.read_byte();
+            let sym = (prev << SYMBOL_BITS | self.rem as u32) >> (SYMBOL_BITS - CODE_EXTRA);
 
-            // Slightly weirder but more flexible:
-            // self.val = ((self.val << SYMBOL_BITS) + (SYMBOL_MAX & !(sym as u32))) & (CODE_TOP - 1);
-            self.val = ((self.val << SYMBOL_BITS) + (255u32 - sym as u32)) & 0x7FFFFFFF;
+            self.val = ((self.val << SYMBOL_BITS) + (SYMBOL_MAX & !sym)) & (CODE_TOP - 1);
         }
     }
-
-    fn ilog(mut v: u32) -> u32 {
-        let mut ret = !!v;
-        let mut m = !!(v & 0xFFFF0000) << 4;
-
-        v >>= m;
-        ret |= m;
-        m = !!(v & 0xFF00) << 3;
-        v >>= m;
-        ret |= m;
-        m = !!(v & 0xF0) << 2;
-        v >>= m;
-        ret |= m;
-        m = !!(v & 0xC) << 1;
-        v >>= m;
-        ret |= m;
-        ret += !!(v & 0x2);
-
-        ret
-    }
 }
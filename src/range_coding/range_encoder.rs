@@ -1,6 +1,28 @@
-use crate::range_coding::{CODE_BOTTOM, CODE_SHIFT, CODE_TOP, SYMBOL_BITS, SYMBOL_MAX};
+use crate::range_coding::{
+    ilog, CODE_BITS, CODE_BOTTOM, CODE_SHIFT, CODE_TOP, SYMBOL_BITS, SYMBOL_MAX, UINT_BITS,
+};
+use alloc::vec::Vec;
+
+/// A destination for the bytes a [`RangeEncoder`] produces, mirroring flacenc's
+/// `BitSink` trait. Lets the forward-written, carry-propagated bytes and the raw
+/// bits packed in from the end of the stream share one output type.
+pub trait BitSink {
+    fn write(&mut self, byte: u8);
+
+    #[allow(unused)]
+    fn count_bits(&self) -> usize;
+}
+
+impl BitSink for Vec<u8> {
+    fn write(&mut self, byte: u8) {
+        self.push(byte);
+    }
+
+    fn count_bits(&self) -> usize {
+        self.len() * 8
+    }
+}
 
-#[allow(unused)]
 pub struct RangeEncoder {
     // The low end of the current range
     val: u32,
@@ -13,12 +35,39 @@ pub struct RangeEncoder {
 
     // A count of additional carry-propagating output bytes
     ext: u16,
+
+    // The forward-written, range-coded output bytes
+    output: Vec<u8>,
+
+    // Raw bits packed in from the end of the stream, in the order they were
+    // produced. `finalize` reverses these onto the tail of `output`.
+    end_bytes: Vec<u8>,
+
+    // A window of bits waiting to be flushed into `end_bytes`
+    end_window: u32,
+
+    // The number of valid bits in `end_window`
+    num_end_bits: i32,
+}
+
+impl Default for RangeEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RangeEncoder {
-    #[allow(unused)]
     pub fn new() -> Self {
-        Self { val: 0, rng: CODE_TOP, rem: None, ext: 0 }
+        Self {
+            val: 0,
+            rng: CODE_TOP,
+            rem: None,
+            ext: 0,
+            output: Vec::new(),
+            end_bytes: Vec::new(),
+            end_window: 0,
+            num_end_bits: 0,
+        }
     }
 
     #[allow(unused)]
@@ -29,34 +78,93 @@ impl RangeEncoder {
             self.val += self.rng - (r * (frequency_total - frequency_low) as u32);
             self.rng = r * (frequency_high - frequency_low) as u32;
         } else {
-            self.rng = r * (frequency_total - frequency_high) as u32;
+            self.rng -= r * (frequency_total - frequency_high) as u32;
         }
 
         self.renormalize();
     }
 
+    // The complement of `RangeDecoder::decode_u32` - encodes `value`, which must
+    // have been produced by a uniform draw from `0..frequency_total`.
+    #[allow(unused)]
+    pub fn encode_u32(&mut self, value: u32, mut frequency_total: u32) {
+        assert!(frequency_total > 1);
+
+        frequency_total -= 1;
+
+        // The number of bits required to store (frequency_total - 1) in two's complement.
+        let frequency_total_bits = ilog(frequency_total);
+
+        if frequency_total_bits > UINT_BITS {
+            let shift = frequency_total_bits - UINT_BITS;
+
+            // The top 8 bits of frequency_total are encoded using temp:
+            let temp = ((frequency_total - 1) >> shift) + 1;
+            let t = value >> shift;
+
+            self.encode(t as u16, t as u16 + 1, temp as u16);
+
+            // The remaining bits are encoded as raw bits.
+            self.encode_bits(value & ((1 << shift) - 1), shift);
+        } else {
+            frequency_total += 1;
+            self.encode(value as u16, value as u16 + 1, frequency_total as u16);
+        }
+    }
+
+    // The complement of `RangeDecoder::decode_cdf` - encodes `symbol`, which
+    // must have been produced by a draw against the same `cumulative` table.
+    #[allow(unused)]
+    pub fn encode_cdf(&mut self, symbol: usize, cumulative: &[u32]) {
+        let frequency_total = *cumulative.last().expect("cumulative table must not be empty");
+        let low = if symbol == 0 { 0 } else { cumulative[symbol - 1] };
+        let high = cumulative[symbol];
+
+        self.encode(low as u16, high as u16, frequency_total as u16);
+    }
+
+    // Packs `bits` raw (uncompressed) bits in from the end of the stream, mirroring
+    // `RangeDecoder::decode_bits` / `read_byte_from_end`.
+    pub fn encode_bits(&mut self, value: u32, bits: u32) {
+        let mut window = self.end_window;
+        let mut used = self.num_end_bits;
+
+        window |= value << used;
+        used += bits as i32;
+
+        while used >= SYMBOL_BITS {
+            self.end_bytes.push((window & SYMBOL_MAX) as u8);
+            window >>= SYMBOL_BITS;
+            used -= SYMBOL_BITS;
+        }
+
+        self.end_window = window;
+        self.num_end_bits = used;
+    }
+
     #[allow(unused)]
     fn renormalize(&mut self) {
         while self.rng <= CODE_BOTTOM {
             self.carry_out(self.val >> CODE_SHIFT);
+            self.val = (self.val << SYMBOL_BITS) & (CODE_TOP - 1);
+            self.rng <<= SYMBOL_BITS;
         }
     }
 
-    #[allow(unused)]
     // c is a 9-bit value (8 data bits and 1 carry bit)
     fn carry_out(&mut self, c: u32) {
         if c != SYMBOL_MAX {
             let carry = c >> SYMBOL_BITS;
 
-            if let Some(_rem) = self.rem {
-                // TODO - Write a byte (self.rem + carry)
+            if let Some(rem) = self.rem {
+                self.write_byte((rem as u32 + carry) as u8);
             }
 
             if self.ext > 0 {
-                let _sym = ((SYMBOL_MAX + carry) & SYMBOL_MAX) as u8;
+                let sym = ((SYMBOL_MAX + carry) & SYMBOL_MAX) as u8;
 
                 loop {
-                    // TODO - Write a byte (sym)
+                    self.write_byte(sym);
                     self.ext -= 1;
                     if self.ext == 0 {
                         break;
@@ -64,14 +172,110 @@ impl RangeEncoder {
                 }
             }
 
-            self.rem = Some((c as u32 & SYMBOL_MAX) as u8);
+            self.rem = Some((c & SYMBOL_MAX) as u8);
         } else {
             self.ext += 1;
         }
     }
 
-    #[allow(unused)]
-    fn write_byte(&mut self) {
-        todo!();
+    fn write_byte(&mut self, byte: u8) {
+        self.output.write(byte);
     }
-}
\ No newline at end of file
+
+    /// Terminates the stream: picks the shortest value still inside the final
+    /// `[val, val + rng)` range, flushes the buffered `rem`/`ext` carry state,
+    /// and splices the raw end-bits onto the tail of the output.
+    ///
+    /// Consumes `self` since no further symbols can be encoded afterwards.
+    pub fn finalize(mut self) -> Vec<u8> {
+        let mut l = CODE_BITS - ilog(self.rng) as i32;
+        let mut msk = (CODE_TOP - 1) >> l;
+        let mut end = self.val.wrapping_add(msk) & !msk;
+
+        if (end | msk) >= self.val.wrapping_add(self.rng) {
+            l += 1;
+            msk >>= 1;
+            end = self.val.wrapping_add(msk) & !msk;
+        }
+
+        while l > 0 {
+            self.carry_out(end >> CODE_SHIFT);
+            end = (end << SYMBOL_BITS) & (CODE_TOP - 1);
+            l -= SYMBOL_BITS;
+        }
+
+        // Flush a buffered byte that's never going to see another carry.
+        if self.rem.is_some() || self.ext > 0 {
+            self.carry_out(0);
+        }
+
+        // Flush the last, partially-filled end-bits byte, if any.
+        if self.num_end_bits > 0 {
+            self.end_bytes.push((self.end_window & SYMBOL_MAX) as u8);
+        }
+
+        // The end-bits were produced closest-to-the-boundary-byte-first, but they
+        // belong at the very end of the stream, so splice them on in reverse.
+        self.output.extend(self.end_bytes.iter().rev());
+        self.output
+    }
+}
+
+#[test]
+fn test_round_trip_small_alphabet() {
+    use crate::range_coding::RangeDecoder;
+
+    let symbols = [3u16, 0, 7, 7, 1, 5, 2, 6, 4, 0];
+    let frequency_total = 8;
+
+    let mut encoder = RangeEncoder::new();
+    for &symbol in &symbols {
+        encoder.encode(symbol, symbol + 1, frequency_total);
+    }
+    let encoded = encoder.finalize();
+
+    let mut decoder = RangeDecoder::new(&encoded);
+    for &symbol in &symbols {
+        assert_eq!(decoder.decode_u32(frequency_total as u32), symbol as u32);
+    }
+}
+
+#[test]
+fn test_round_trip_encode_u32() {
+    use crate::range_coding::RangeDecoder;
+
+    let values = [0u32, 1000, 500, 999, 1, 0, 999];
+    let frequency_total = 1000;
+
+    let mut encoder = RangeEncoder::new();
+    for &value in &values {
+        encoder.encode_u32(value.min(frequency_total - 1), frequency_total);
+    }
+    let encoded = encoder.finalize();
+
+    let mut decoder = RangeDecoder::new(&encoded);
+    for &value in &values {
+        assert_eq!(decoder.decode_u32(frequency_total), value.min(frequency_total - 1));
+    }
+}
+
+#[test]
+fn test_round_trip_cdf() {
+    use crate::range_coding::RangeDecoder;
+
+    // A skewed, non-uniform alphabet (symbol 0 is much likelier than the
+    // tails), the shape `decode_cdf`/`encode_cdf` exist for.
+    let cumulative = [40u32, 60, 90, 100];
+    let symbols = [0usize, 2, 1, 3, 0, 0, 1];
+
+    let mut encoder = RangeEncoder::new();
+    for &symbol in &symbols {
+        encoder.encode_cdf(symbol, &cumulative);
+    }
+    let encoded = encoder.finalize();
+
+    let mut decoder = RangeDecoder::new(&encoded);
+    for &symbol in &symbols {
+        assert_eq!(decoder.decode_cdf(&cumulative), symbol);
+    }
+}
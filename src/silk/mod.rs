@@ -0,0 +1,588 @@
+//! The SILK decode subsystem: LSF/gain/LTP parameter decoding, LSF-to-LPC
+//! conversion, and LPC+LTP synthesis.
+//!
+//! This is a partial implementation, not a full RFC 6716 decoder yet:
+//! `decode_lsf` now picks its stage-1 prototype from a genuinely
+//! bandwidth-dependent codebook and decodes each stage-2 residual from a
+//! skewed (non-flat) distribution via [`RangeDecoder::decode_cdf`], and
+//! `decode_excitation_block` now decodes a real recursive shell-coded pulse
+//! block (a pulse count, then a binomial-weighted binary split down to
+//! individual positions) instead of a single flat magnitude+sign per sample.
+//! But neither the stage-1 codebook entries nor the skewed distributions are
+//! libopus's actual tables - RFC 6716 doesn't publish those as a formula,
+//! only as literal transcribed tables this crate doesn't have a verified
+//! source for, so they're this crate's own originally-derived stand-ins with
+//! the right shape (bandwidth-dependent, peaked towards zero/small counts).
+//! So this still can't parse real-world Opus bitstreams, only packets
+//! produced by this crate's own `decode_frame` reader order.
+//!
+//! FIXME(bschwind) - BLOCKED, not just undocumented: this was asked to carry
+//! the real RFC 6716 section 4.2.7 stage-1 NLSF codebook and excitation ICDF
+//! tables, plus a conformance test against an actual libopus-encoded SILK
+//! fixture. Neither has happened. The tables are large (per-bandwidth
+//! stage-1 codebooks are tens of 16-entry rows; the excitation ICDFs are
+//! similarly sized) and this crate has no copy of the RFC or libopus source
+//! on hand to transcribe them from accurately - typing them out from memory
+//! risks silently-wrong entries that are far worse than today's honestly-
+//! labeled stand-ins, since a subtly wrong "real" table looks authoritative
+//! right up until it corrupts a decode in a way nothing here would catch.
+//! A conformance fixture needs a real libopus-encoded SILK packet, and
+//! there's neither network access nor a local Opus encoder in this sandbox
+//! to produce one (the same blocker `decoder.rs`'s sin.opus test flags).
+//! Both parts need a transcription source and a fixture supplied from
+//! outside this environment before they can be done for real - flagging for
+//! the requester rather than re-closing this quietly a second time.
+
+use crate::{math, range_coding::RangeDecoder, Bandwidth};
+use alloc::{vec, vec::Vec};
+
+/// SILK's LPC order. Real SILK varies this with bandwidth (10 for
+/// narrowband/mediumband, 16 for wideband), but every config this decoder
+/// sees tops out at wideband, so a single fixed order covers them all
+/// without a per-bandwidth table.
+const LPC_ORDER: usize = 16;
+
+/// SILK encodes in 5ms subframes; a 20ms frame is 4 of them.
+const SUBFRAME_MS: u32 = 5;
+
+/// SILK's own internal sample rate. Narrowband/mediumband input is
+/// upsampled to this rate on the encode side and downsampled back on
+/// decode; this decoder always runs the LPC synthesis filter at wideband
+/// rate and resamples once at the end, rather than varying the filter
+/// rate per bandwidth.
+const INTERNAL_RATE: u32 = 16_000;
+
+/// Inter-frame SILK decoder state: the LPC synthesis filter's history (the
+/// last `LPC_ORDER` output samples), the long-term-prediction history
+/// buffer used for pitch prediction, the previous frame's LSFs (the
+/// backward-prediction reference for stage-2 LSF decoding), and the
+/// previous frame's gain (the reference for each subframe's quantized gain
+/// delta).
+pub struct SilkState {
+    lpc_history: Vec<f32>,
+    ltp_history: Vec<f32>,
+    prev_lsf: [f32; LPC_ORDER],
+    prev_gain: f32,
+}
+
+impl SilkState {
+    pub fn new() -> Self {
+        Self {
+            lpc_history: vec![0.0; LPC_ORDER],
+            ltp_history: vec![0.0; MAX_PITCH_LAG],
+            prev_lsf: default_lsf(),
+            prev_gain: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.lpc_history.iter_mut().for_each(|s| *s = 0.0);
+        self.ltp_history.iter_mut().for_each(|s| *s = 0.0);
+        self.prev_lsf = default_lsf();
+        self.prev_gain = 0.0;
+    }
+}
+
+impl Default for SilkState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// An even spread across (0, pi), the usual silent-signal starting point for
+// backward LSF prediction before any real frame has been decoded.
+fn default_lsf() -> [f32; LPC_ORDER] {
+    let mut lsf = [0.0f32; LPC_ORDER];
+    for (i, slot) in lsf.iter_mut().enumerate() {
+        *slot = core::f32::consts::PI * (i + 1) as f32 / (LPC_ORDER + 1) as f32;
+    }
+    lsf
+}
+
+const MAX_PITCH_LAG: usize = 620; // ~2.5ms to 40ms of pitch lag at 16kHz.
+const MIN_PITCH_LAG: usize = 32;
+
+/// Decodes one SILK frame of `frame_size` samples at `output_rate`, from a
+/// packet whose TOC header declared `bandwidth` (selecting `decode_lsf`'s
+/// stage-1 codebook).
+///
+/// This covers the core pipeline (VAD/LBRR flags, per-subframe gains,
+/// backward-predicted LSFs converted to LPC via the cosine-polynomial
+/// expansion, a pitch-lag-and-taps LTP stage, and a shell-coded excitation
+/// block per subframe run through the short-term synthesis filter), but
+/// `decode_lsf` and `decode_excitation_block` below still don't implement
+/// libopus's exact tables - see the module doc for what's still
+/// originally-derived rather than transcribed from the spec.
+pub fn decode_frame(
+    range_decoder: &mut RangeDecoder,
+    state: &mut SilkState,
+    frame_size: usize,
+    output_rate: u32,
+    bandwidth: Bandwidth,
+) -> Vec<f32> {
+    let _voice_activity = range_decoder.decode_bit_logp(1);
+    let _lbrr_present = range_decoder.decode_bit_logp(1);
+
+    let internal_frame_size = frame_size * INTERNAL_RATE as usize / output_rate as usize;
+    let frame_ms = (frame_size as u64 * 1000 / output_rate as u64).max(1) as u32;
+    let num_subframes = ((frame_ms / SUBFRAME_MS).max(1)) as usize;
+    let subframe_len = internal_frame_size / num_subframes;
+
+    let lsf = decode_lsf(range_decoder, state, bandwidth);
+    let lpc = lsf_to_lpc(&lsf);
+    state.prev_lsf = lsf;
+
+    let mut internal_samples = Vec::with_capacity(subframe_len * num_subframes);
+
+    for _ in 0..num_subframes {
+        let gain = decode_gain(range_decoder, state);
+        let (pitch_lag, ltp_taps) = decode_ltp(range_decoder);
+        let excitation_block = decode_excitation_block(range_decoder, subframe_len);
+
+        for &excitation_sample in &excitation_block {
+            let excitation = excitation_sample * gain;
+
+            let ltp_contribution = ltp_predict(&state.ltp_history, pitch_lag, &ltp_taps);
+            let predicted = excitation + ltp_contribution;
+
+            let sample = lpc_synthesize(&state.lpc_history, &lpc, predicted);
+
+            state.lpc_history.remove(0);
+            state.lpc_history.push(sample);
+
+            state.ltp_history.remove(0);
+            state.ltp_history.push(sample);
+
+            internal_samples.push(sample);
+        }
+    }
+
+    resample(&internal_samples, INTERNAL_RATE, output_rate)
+}
+
+/// Conceals a lost SILK frame the same way the CELT path does: replays the
+/// synthesis filter with no fresh excitation (so it rings out on its own
+/// pole history) and lets the LTP history decay under `attenuation`.
+pub fn conceal_frame(
+    state: &mut SilkState,
+    frame_size: usize,
+    output_rate: u32,
+    attenuation: f32,
+) -> Vec<f32> {
+    let lpc = lsf_to_lpc(&state.prev_lsf);
+    let internal_frame_size = frame_size * INTERNAL_RATE as usize / output_rate as usize;
+
+    let mut internal_samples = Vec::with_capacity(internal_frame_size);
+
+    for _ in 0..internal_frame_size {
+        let sample = lpc_synthesize(&state.lpc_history, &lpc, 0.0) * attenuation;
+
+        state.lpc_history.remove(0);
+        state.lpc_history.push(sample);
+
+        state.ltp_history.remove(0);
+        state.ltp_history.push(sample);
+
+        internal_samples.push(sample);
+    }
+
+    resample(&internal_samples, INTERNAL_RATE, output_rate)
+}
+
+const STAGE1_CODEBOOK_SIZE: u32 = 32;
+const RESIDUAL_HALF_RANGE: i32 = 16;
+const BACKWARD_PREDICTION_WEIGHT: f32 = 0.25;
+
+// How strongly a bandwidth's stage-1 codebook bows away from an even LSF
+// spread. Wider bandwidths carry proportionally more high-frequency content,
+// so their prototypes lean further from flat spacing; this is this crate's
+// own invented stand-in for SILK's real per-bandwidth codebook tables (RFC
+// 6716 doesn't publish those as a formula, only as literal tables), chosen
+// only to make each bandwidth's codebook genuinely distinct.
+fn bandwidth_tilt(bandwidth: Bandwidth) -> f32 {
+    match bandwidth {
+        Bandwidth::Narrow => 0.15,
+        Bandwidth::Medium => 0.25,
+        Bandwidth::Wide => 0.35,
+        Bandwidth::SuperWide => 0.45,
+        Bandwidth::Full => 0.55,
+    }
+}
+
+// The stage-1 prototype `stage1_index` (0..STAGE1_CODEBOOK_SIZE) names,
+// within `bandwidth`'s codebook: an even LSF spread, bowed in or out by an
+// amount that grows with both the bandwidth's tilt and how far `stage1_index`
+// sits from the codebook's midpoint.
+fn stage1_prototype(bandwidth: Bandwidth, stage1_index: u32) -> [f32; LPC_ORDER] {
+    let tilt = bandwidth_tilt(bandwidth);
+    let lean = (stage1_index as f32 / (STAGE1_CODEBOOK_SIZE - 1) as f32) * 2.0 - 1.0;
+
+    let mut lsf = [0.0f32; LPC_ORDER];
+    for (i, slot) in lsf.iter_mut().enumerate() {
+        let even = core::f32::consts::PI * (i + 1) as f32 / (LPC_ORDER + 1) as f32;
+        *slot = even + lean * tilt * math::sin(even);
+    }
+    lsf
+}
+
+// A symmetric, triangular (peaked-at-zero) cumulative table over
+// `-half..=half`, used in place of SILK's real per-coefficient Laplace-like
+// ICDF for the stage-2 residual: a skewed (not flat) distribution without
+// claiming to be libopus's exact one.
+fn triangular_cdf(half: i32) -> Vec<u32> {
+    let mut cumulative = Vec::with_capacity((2 * half + 1) as usize);
+    let mut total = 0u32;
+
+    for value in -half..=half {
+        total += (half + 1 - value.abs()) as u32;
+        cumulative.push(total);
+    }
+
+    cumulative
+}
+
+// Decodes the normalized LSFs: a stage-1 index picking a bandwidth-specific
+// prototype codebook entry, then one skewed range-coded residual per
+// coefficient refining it, added on top of a backward prediction from the
+// previous frame's LSFs (weighted so a silent/steady voice converges rather
+// than resetting every frame).
+fn decode_lsf(
+    range_decoder: &mut RangeDecoder,
+    state: &SilkState,
+    bandwidth: Bandwidth,
+) -> [f32; LPC_ORDER] {
+    let stage1_index = range_decoder.decode_u32(STAGE1_CODEBOOK_SIZE);
+    let prototype = stage1_prototype(bandwidth, stage1_index);
+    let residual_cdf = triangular_cdf(RESIDUAL_HALF_RANGE);
+
+    let mut lsf = [0.0f32; LPC_ORDER];
+    let step = 1.0 / (RESIDUAL_HALF_RANGE + 1) as f32;
+
+    for (i, slot) in lsf.iter_mut().enumerate() {
+        let raw = range_decoder.decode_cdf(&residual_cdf) as i32 - RESIDUAL_HALF_RANGE;
+        let residual = raw as f32 * step;
+
+        let predicted = prototype[i] + state.prev_lsf[i] * BACKWARD_PREDICTION_WEIGHT;
+        *slot = predicted + residual;
+    }
+
+    // Keep the LSFs ordered and inside (0, pi); SILK's stability check does
+    // the same clamp-and-sort before the LPC conversion runs.
+    lsf.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    for (i, slot) in lsf.iter_mut().enumerate() {
+        let floor = core::f32::consts::PI * i as f32 / (LPC_ORDER + 1) as f32;
+        let ceil = core::f32::consts::PI * (i + 2) as f32 / (LPC_ORDER + 1) as f32;
+        *slot = slot.max(floor + 0.001).min(ceil - 0.001);
+    }
+
+    lsf
+}
+
+// Converts normalized LSFs (strictly increasing values in (0, pi)) to LPC
+// coefficients via the classic cosine-polynomial expansion: the even- and
+// odd-indexed LSFs each build a half-order polynomial of `(1 - 2cos(w)z^-1 +
+// z^-2)` factors, one is extended by `(1 + z^-1)` and the other by `(1 -
+// z^-1)`, and summing the two recovers twice the LPC polynomial.
+fn lsf_to_lpc(lsf: &[f32; LPC_ORDER]) -> [f32; LPC_ORDER] {
+    let half = LPC_ORDER / 2;
+
+    let p_cosines: Vec<f32> = (0..half).map(|i| math::cos(lsf[2 * i])).collect();
+    let q_cosines: Vec<f32> = (0..half).map(|i| math::cos(lsf[2 * i + 1])).collect();
+
+    let p_half = polynomial_from_cosines(&p_cosines);
+    let q_half = polynomial_from_cosines(&q_cosines);
+
+    let p_full = convolve(&p_half, &[1.0, 1.0]);
+    let q_full = convolve(&q_half, &[1.0, -1.0]);
+
+    let mut lpc = [0.0f32; LPC_ORDER];
+    for (k, slot) in lpc.iter_mut().enumerate() {
+        *slot = -(p_full[k + 1] + q_full[k + 1]) / 2.0;
+    }
+
+    lpc
+}
+
+// Builds `product((1 - 2*cos_i*z^-1 + z^-2))` over `cosines`, as a
+// coefficient vector from the constant term up.
+fn polynomial_from_cosines(cosines: &[f32]) -> Vec<f32> {
+    let mut poly = vec![1.0f32];
+
+    for &cosine in cosines {
+        poly = convolve(&poly, &[1.0, -2.0 * cosine, 1.0]);
+    }
+
+    poly
+}
+
+fn convolve(a: &[f32], b: &[f32]) -> Vec<f32> {
+    let mut out = vec![0.0f32; a.len() + b.len() - 1];
+
+    for (i, &ai) in a.iter().enumerate() {
+        for (j, &bj) in b.iter().enumerate() {
+            out[i + j] += ai * bj;
+        }
+    }
+
+    out
+}
+
+// A quantized log-domain gain, predicted from the previous subframe so
+// gently-changing loudness costs only a small delta.
+fn decode_gain(range_decoder: &mut RangeDecoder, state: &mut SilkState) -> f32 {
+    const GAIN_RANGE: u32 = 33;
+    const GAIN_BIAS: i32 = 16;
+
+    let raw = range_decoder.decode_u32(GAIN_RANGE) as i32 - GAIN_BIAS;
+    let log_gain = state.prev_gain + raw as f32 * 0.125;
+
+    state.prev_gain = log_gain;
+    math::exp(log_gain)
+}
+
+// Decodes the long-term (pitch) prediction parameters: a lag into the LTP
+// history buffer and a 5-tap filter centered on it.
+fn decode_ltp(range_decoder: &mut RangeDecoder) -> (usize, [f32; 5]) {
+    let lag_range = (MAX_PITCH_LAG - MIN_PITCH_LAG) as u32;
+    let lag = MIN_PITCH_LAG + range_decoder.decode_u32(lag_range) as usize;
+
+    const TAP_RANGE: u32 = 17;
+    const TAP_BIAS: i32 = 8;
+
+    let mut taps = [0.0f32; 5];
+    for tap in taps.iter_mut() {
+        let raw = range_decoder.decode_u32(TAP_RANGE) as i32 - TAP_BIAS;
+        *tap = raw as f32 / (TAP_BIAS as f32 * 4.0);
+    }
+
+    (lag, taps)
+}
+
+fn ltp_predict(history: &[f32], lag: usize, taps: &[f32; 5]) -> f32 {
+    let len = history.len() as isize;
+    let mut prediction = 0.0f32;
+
+    for (i, &tap) in taps.iter().enumerate() {
+        // The 5 taps straddle the pitch period, two samples either side.
+        let tap_offset = i as isize - 2;
+        let index = len - lag as isize + tap_offset;
+
+        if index >= 0 && index < len {
+            prediction += tap * history[index as usize];
+        }
+    }
+
+    prediction
+}
+
+// The most pulses a single subframe's shell code can spend; caps how large
+// `binomial_cdf`'s table grows, the same role `pvq::pulse_budget`'s cap
+// plays for CELT's combinatorial codebook.
+const MAX_SUBFRAME_PULSES: u32 = 8;
+const LSB_BITS: u32 = 2;
+
+// A one-sided cumulative table over `0..=max`, decreasing from `max` towards
+// zero, standing in for SILK's real signal-type/quantization-offset-dependent
+// pulse-count ICDF (RFC 6716 section 4.2.7.8.1): most subframes spend few
+// pulses, so low counts should be (and here are) likelier than high ones.
+fn decreasing_cdf(max: u32) -> Vec<u32> {
+    let mut cumulative = Vec::with_capacity((max + 1) as usize);
+    let mut total = 0u32;
+
+    for value in 0..=max {
+        total += max + 1 - value;
+        cumulative.push(total);
+    }
+
+    cumulative
+}
+
+// Row `k` of Pascal's triangle: `row[j]` is `C(k, j)`.
+fn binomial_row(k: u32) -> Vec<u64> {
+    let mut row = vec![1u64];
+
+    for i in 1..=k {
+        let mut next = vec![1u64; (i + 1) as usize];
+        for j in 1..i as usize {
+            next[j] = row[j - 1] + row[j];
+        }
+        row = next;
+    }
+
+    row
+}
+
+// The cumulative table a shell-split node uses to decide how many of its `k`
+// pulses fall in its left child: each pulse independently lands left or
+// right with equal probability, so the left count follows `Binomial(k,
+// 1/2)`, whose weights are exactly `C(k, j)`.
+fn binomial_cdf(k: u32) -> Vec<u32> {
+    let mut total = 0u32;
+    binomial_row(k)
+        .into_iter()
+        .map(|count| {
+            total += count as u32;
+            total
+        })
+        .collect()
+}
+
+// The recursive shell decomposition: splits `k` pulses across a block of
+// `size` positions by repeatedly deciding (via `binomial_cdf`) how many of
+// the remaining pulses fall in the left half versus the right half, down to
+// individual positions - RFC 6716 section 4.2.7.8.2's "shell" structure,
+// without claiming to reproduce libopus's exact per-level ICDF tables.
+fn shell_split(range_decoder: &mut RangeDecoder, k: u32, size: usize) -> Vec<u32> {
+    if size == 1 {
+        // Nowhere left to split: whatever pulses remain all land here.
+        return vec![k];
+    }
+    if k == 0 {
+        // No pulses left to place and no choice to decode either way.
+        return vec![0; size];
+    }
+
+    let left_size = size / 2;
+    let right_size = size - left_size;
+
+    let cdf = binomial_cdf(k);
+    let left_count = range_decoder.decode_cdf(&cdf) as u32;
+    let right_count = k - left_count;
+
+    let mut pulses = shell_split(range_decoder, left_count, left_size);
+    pulses.extend(shell_split(range_decoder, right_count, right_size));
+    pulses
+}
+
+// One subframe's excitation block: a shell-coded pulse count per position
+// (see `shell_split`), each nonzero position then refined with a couple of
+// raw low-significance bits and a sign - mirroring the magnitude/sign split
+// the CELT band-shape decoder uses, but for a combinatorially-placed block
+// of pulses instead of a single flat draw per sample.
+fn decode_excitation_block(range_decoder: &mut RangeDecoder, size: usize) -> Vec<f32> {
+    let pulse_count_cdf = decreasing_cdf(MAX_SUBFRAME_PULSES);
+    let pulse_count = range_decoder.decode_cdf(&pulse_count_cdf) as u32;
+
+    let magnitudes = shell_split(range_decoder, pulse_count, size);
+
+    magnitudes
+        .into_iter()
+        .map(|magnitude| {
+            if magnitude == 0 {
+                return 0.0;
+            }
+
+            let lsbs = range_decoder.decode_bits(LSB_BITS) as f32;
+            let sign = if range_decoder.decode_bit_logp(1) { -1.0 } else { 1.0 };
+            let value = magnitude as f32 + lsbs / (1 << LSB_BITS) as f32;
+
+            sign * value / MAX_SUBFRAME_PULSES as f32
+        })
+        .collect()
+}
+
+// The short-term (all-pole) LPC synthesis filter: this frame's sample is the
+// incoming excitation plus the predicted contribution of the last
+// `LPC_ORDER` output samples.
+fn lpc_synthesize(history: &[f32], lpc: &[f32; LPC_ORDER], excitation: f32) -> f32 {
+    let mut prediction = 0.0f32;
+
+    for (k, &coefficient) in lpc.iter().enumerate() {
+        let index = history.len() - 1 - k;
+        prediction += coefficient * history[index];
+    }
+
+    excitation + prediction
+}
+
+// SILK always runs its filters at a fixed internal rate and leaves matching
+// the stream's actual sample rate to a resampler. Real SILK uses a
+// polyphase FIR resampler; linear interpolation is the straightforward
+// stand-in here.
+fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || input.is_empty() {
+        return input.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = crate::math::round(input.len() as f64 / ratio) as usize;
+
+    (0..out_len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position as usize;
+            let frac = (position - index as f64) as f32;
+
+            let a = input[index.min(input.len() - 1)];
+            let b = input[(index + 1).min(input.len() - 1)];
+
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+#[test]
+fn test_decode_frame_produces_finite_output_for_every_bandwidth() {
+    // Arbitrary but non-trivial packet bytes - any bitstream is "valid" to a
+    // range coder, so this isn't asserting a particular decoded waveform,
+    // just that the new stage-1/residual/shell-code decoding runs to
+    // completion (consuming the whole range-coded block without panicking)
+    // and produces finite, sane-range samples for each bandwidth's codebook.
+    let data: Vec<u8> = (0..64u8).map(|i| i.wrapping_mul(37).wrapping_add(11)).collect();
+    const SAMPLE_RATE: u32 = 16_000;
+    const FRAME_SIZE: usize = 320; // 20ms at 16kHz
+
+    for bandwidth in
+        [Bandwidth::Narrow, Bandwidth::Medium, Bandwidth::Wide, Bandwidth::SuperWide, Bandwidth::Full]
+    {
+        let mut range_decoder = RangeDecoder::new(&data);
+        let mut state = SilkState::new();
+
+        let samples =
+            decode_frame(&mut range_decoder, &mut state, FRAME_SIZE, SAMPLE_RATE, bandwidth);
+
+        assert_eq!(samples.len(), FRAME_SIZE);
+        assert!(samples.iter().all(|sample| sample.is_finite()));
+    }
+}
+
+#[test]
+fn test_lsf_to_lpc_produces_a_stable_filter() {
+    // A mildly perturbed (but still strictly increasing, in-range) set of
+    // LSFs, clamped the same way `decode_lsf` clamps its output - a more
+    // representative case than the perfectly even `default_lsf` spread,
+    // which collapses to near-zero LPC coefficients.
+    let mut lsf = default_lsf();
+    for (i, slot) in lsf.iter_mut().enumerate() {
+        *slot += 0.05 * (i as f32 * 0.7).sin();
+    }
+    lsf.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    for (i, slot) in lsf.iter_mut().enumerate() {
+        let floor = core::f32::consts::PI * i as f32 / (LPC_ORDER + 1) as f32;
+        let ceil = core::f32::consts::PI * (i + 2) as f32 / (LPC_ORDER + 1) as f32;
+        *slot = slot.max(floor + 0.001).min(ceil - 0.001);
+    }
+
+    let lpc = lsf_to_lpc(&lsf);
+    assert!(lpc.iter().all(|c| c.is_finite()));
+
+    // Ring an impulse through the synthesis filter for a few hundred
+    // samples; a stable filter (all poles inside the unit circle) decays
+    // towards zero instead of diverging.
+    let mut history = vec![0.0f32; LPC_ORDER];
+    let mut excitation = 1.0f32;
+    let mut peak = 0.0f32;
+
+    for _ in 0..500 {
+        let sample = lpc_synthesize(&history, &lpc, excitation);
+        history.remove(0);
+        history.push(sample);
+        excitation = 0.0;
+        peak = peak.max(sample.abs());
+    }
+
+    assert!(peak.is_finite() && peak < 1e6, "impulse response diverged: peak {peak}");
+
+    let tail_energy: f32 = history.iter().map(|sample| sample.abs()).sum();
+    assert!(tail_energy < 0.01, "impulse response didn't decay: tail energy {tail_energy}");
+}